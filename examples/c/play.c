@@ -0,0 +1,59 @@
+/*
+ * Plays one game through the C API, preferring down, left, right, then up.
+ *
+ *   cargo build --release
+ *   cc examples/c/play.c -Iinclude -Ltarget/release -lr2048 -o play
+ *   LD_LIBRARY_PATH=target/release ./play 42
+ */
+
+#include <stdio.h>
+#include <stdlib.h>
+
+#include "r2048.h"
+
+static void print_board(const R2048Game *game) {
+    for (uint32_t row = 0; row < 4; row++) {
+        for (uint32_t col = 0; col < 4; col++) {
+            printf("%6u", r2048_cell(game, row, col));
+        }
+        printf("\n");
+    }
+}
+
+int main(int argc, char **argv) {
+    if (r2048_abi_version() != R2048_ABI_VERSION) {
+        fprintf(stderr, "libr2048 speaks ABI %u, this program was built for %u\n",
+                r2048_abi_version(), R2048_ABI_VERSION);
+        return 1;
+    }
+
+    uint64_t seed = argc > 1 ? strtoull(argv[1], NULL, 10) : 0;
+    const int order[4] = {1, 2, 3, 0};
+
+    R2048Game *game = r2048_new(seed);
+    unsigned moves = 0;
+    while (!r2048_game_over(game)) {
+        for (int i = 0; i < 4; i++) {
+            if (r2048_move(game, order[i]) == 1) {
+                moves++;
+                break;
+            }
+        }
+    }
+
+    print_board(game);
+    printf("moves %u, score %u, merge score %u\n", moves, r2048_score(game), r2048_merge_score(game));
+
+    size_t len = r2048_serialize(game, NULL, 0);
+    char *text = malloc(len + 1);
+    r2048_serialize(game, text, len + 1);
+    printf("state: %s\n", text);
+
+    R2048Game *copy = r2048_deserialize(text, seed);
+    printf("restored score %u\n", r2048_score(copy));
+
+    free(text);
+    r2048_free(copy);
+    r2048_free(game);
+    return 0;
+}
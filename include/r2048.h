@@ -0,0 +1,148 @@
+/*
+ * C API for the r2048 game engine.
+ *
+ * Generated from src/ffi.rs, do not edit. Regenerate with:
+ *
+ *   cbindgen --config cbindgen.toml --output include/r2048.h src/ffi.rs
+ */
+
+#ifndef R2048_H
+#define R2048_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+/*
+ Bumped whenever a function signature or the serialized format changes.
+ */
+#define R2048_ABI_VERSION 1
+
+/*
+ Opaque game handle.
+ */
+typedef struct R2048Game R2048Game;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/*
+ Returns [`R2048_ABI_VERSION`].
+ */
+uint32_t r2048_abi_version(void);
+
+/*
+ Creates a game whose spawns are drawn from `seed`, with the two starting tiles placed.
+ Release it with [`r2048_free`].
+ */
+struct R2048Game *r2048_new(uint64_t seed);
+
+/*
+ Destroys a game created by [`r2048_new`] or [`r2048_deserialize`].
+
+ # Safety
+
+ `game` must be null or a pointer returned by this library that was not freed yet.
+ */
+void r2048_free(struct R2048Game *game);
+
+/*
+ Restarts the spawn sequence from `seed` without touching the cells.
+
+ # Safety
+
+ `game` must be null or a live game handle.
+ */
+void r2048_seed(struct R2048Game *game, uint64_t seed);
+
+/*
+ Clears the board and places two new starting tiles.
+
+ # Safety
+
+ `game` must be null or a live game handle.
+ */
+void r2048_reset(struct R2048Game *game);
+
+/*
+ Slides the tiles (`0` up, `1` down, `2` left, `3` right) and spawns a tile if anything moved.
+ Returns `1` if the board changed, `0` if the move was a no-op and `-1` for an unknown
+ direction.
+
+ # Safety
+
+ `game` must be null or a live game handle.
+ */
+int r2048_move(struct R2048Game *game, int direction);
+
+/*
+ Value of the cell at `row`, `col`, `0` when empty or out of range.
+
+ # Safety
+
+ `game` must be null or a live game handle.
+ */
+uint32_t r2048_cell(const struct R2048Game *game, uint32_t row, uint32_t col);
+
+/*
+ Sum of the tiles on the board.
+
+ # Safety
+
+ `game` must be null or a live game handle.
+ */
+uint32_t r2048_score(const struct R2048Game *game);
+
+/*
+ Sum of every merge this game, the classic 2048 score.
+
+ # Safety
+
+ `game` must be null or a live game handle.
+ */
+uint32_t r2048_merge_score(const struct R2048Game *game);
+
+/*
+ `1` once no move can change the board, `0` otherwise.
+
+ # Safety
+
+ `game` must be null or a live game handle.
+ */
+int r2048_game_over(const struct R2048Game *game);
+
+/*
+ `1` if a 2048 tile or a bigger one is on the board, `0` otherwise.
+
+ # Safety
+
+ `game` must be null or a live game handle.
+ */
+int r2048_won(const struct R2048Game *game);
+
+/*
+ Writes the state as a NUL-terminated string into `buf` and returns its length without the
+ NUL. If `len` is too small nothing is written, call again with a buffer of at least the
+ returned length plus one. The spawn sequence is not saved.
+
+ # Safety
+
+ `game` must be null or a live game handle, and `buf` must be null or valid for `len` bytes.
+ */
+size_t r2048_serialize(const struct R2048Game *game, char *buf, size_t len);
+
+/*
+ Creates a game from a string written by [`r2048_serialize`], drawing future spawns from
+ `seed`. Returns null if the string is malformed.
+
+ # Safety
+
+ `text` must be null or a valid NUL-terminated string.
+ */
+struct R2048Game *r2048_deserialize(const char *text, uint64_t seed);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* R2048_H */
@@ -0,0 +1,122 @@
+//! The spawner of [`crate::board::Difficulty::Evil`]: instead of a random cell, every tile
+//! goes where it hurts the player most, found by a shallow minimax over the spawns and the
+//! player's replies.
+
+use crate::ai::strategy::heuristic;
+use crate::board::{Board, Difficulty, Direction, Spawns};
+
+/// Value of a position with no move left.
+const LOST: f64 = -1.0e6;
+
+/// Spawns the next tile of a game: where it hurts the player most under
+/// [`Difficulty::Evil`] with random spawns, as [`Board::spawn_one_random`] places it otherwise.
+/// Rollouts and other lookahead call [`Board::spawn_one_random`] directly, the search being
+/// far too slow to repeat at every simulated move.
+pub fn spawn(board: &mut Board) {
+    match (board.rules.difficulty, board.rules.spawns) {
+        (Difficulty::Evil, Spawns::Random) => {
+            let depth = board.rules.evil_depth;
+            board.spawn_with(|board| worst_spawn(board, depth));
+        }
+        _ => board.spawn_one_random(),
+    }
+}
+
+/// The empty cell and tile the evil spawner picks on `board`, out of the tiles of
+/// [`crate::board::Rules::odds`]. It looks `depth` rounds of a spawn and the player's best
+/// reply ahead and keeps the spawn whose best outcome for the player is the lowest,
+/// [`heuristic`] scoring the positions at the end.
+pub fn worst_spawn(board: &Board, depth: u8) -> Option<((usize, usize), u32)> {
+    let mut worst = None;
+    for (cell, next) in spawns(board) {
+        let value = reply_value(&next, depth.max(1));
+        if worst.as_ref().is_none_or(|&(lowest, _)| value < lowest) {
+            worst = Some((value, (cell, next.size[cell.0][cell.1])));
+        }
+    }
+    worst.map(|(_, spawn)| spawn)
+}
+
+/// Every board `board` can become by a spawn, with the cell the tile landed in.
+fn spawns(board: &Board) -> impl Iterator<Item = ((usize, usize), Board)> + '_ {
+    let odds = board.rules.odds();
+    board.spawn_cells().into_iter().flat_map(move |(row, col)| {
+        odds.iter().map(move |&(value, _)| {
+            let mut next = board.clone();
+            next.size[row][col] = value;
+            ((row, col), next)
+        })
+    })
+}
+
+/// Value for the player to move on `board`: the best of its moves, each followed by the worst
+/// spawn for `depth - 1` more rounds.
+fn reply_value(board: &Board, depth: u8) -> f64 {
+    Direction::ALL
+        .into_iter()
+        .filter_map(|direction| board.after(direction))
+        .map(|next| match depth {
+            0 | 1 => heuristic(&next),
+            _ => spawn_value(&next, depth - 1),
+        })
+        .fold(LOST, f64::max)
+}
+
+/// Value for the player of `board` with a tile about to spawn, the spawner picking the worst.
+fn spawn_value(board: &Board, depth: u8) -> f64 {
+    spawns(board)
+        .map(|(_, next)| reply_value(&next, depth))
+        .reduce(f64::min)
+        .unwrap_or_else(|| reply_value(board, depth))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Rules;
+
+    fn board(text: &str, difficulty: Difficulty) -> Board {
+        let mut board = Board::seeded(0);
+        board.rules = Rules {
+            difficulty,
+            ..Rules::default()
+        };
+        board.load_text(text).unwrap();
+        board
+    }
+
+    #[test]
+    fn picks_the_spawn_that_ends_the_game() {
+        // A 2 leaves `2,4/8,2` with no move, a 4 would let the 4s merge.
+        assert_eq!(
+            worst_spawn(&board("2,4/8,0 0", Difficulty::Evil), 1),
+            Some(((1, 1), 2))
+        );
+        // Here the rare 4 is the one that blocks.
+        assert_eq!(
+            worst_spawn(&board("4,2/8,0 0", Difficulty::Evil), 2),
+            Some(((1, 1), 4))
+        );
+        assert_eq!(worst_spawn(&board("2,4/8,16 0", Difficulty::Evil), 1), None);
+    }
+
+    #[test]
+    fn spawns_at_random_unless_evil() {
+        let text = "4,2/8,0 0";
+        for seed in 0..20 {
+            let mut evil = board(text, Difficulty::Evil);
+            evil.reseed(seed);
+            spawn(&mut evil);
+            assert_eq!(evil.to_text(), "4,2/8,4 0");
+        }
+        let twos = (0..20)
+            .filter(|&seed| {
+                let mut normal = board(text, Difficulty::Normal);
+                normal.reseed(seed);
+                spawn(&mut normal);
+                normal.size[1][1] == 2
+            })
+            .count();
+        assert!(twos > 10);
+    }
+}
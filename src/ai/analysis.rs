@@ -0,0 +1,122 @@
+//! Post-game review: re-evaluates every recorded position with [`Expectimax`] and flags the moves
+//! that fell far below the best alternative, measured against the gap between the best and the
+//! worst move of the position.
+
+use crate::ai::strategy::Expectimax;
+use crate::board::{Board, Direction};
+
+/// Search depth used for the review.
+pub const DEPTH: u32 = 2;
+
+/// Share of the gap between the best and the worst move a move has to give away to count as a
+/// blunder.
+pub const BLUNDER_LOSS: f64 = 0.15;
+
+/// Evaluation of one move of a finished game.
+#[derive(Debug, Clone)]
+pub struct MoveReview {
+    /// Position before the move.
+    pub board: Board,
+    pub played: Direction,
+    pub best: Direction,
+    pub played_value: f64,
+    pub best_value: f64,
+    /// Value of the worst legal move.
+    pub worst_value: f64,
+}
+
+impl MoveReview {
+    /// How much of the gap between the best and the worst move was given away, from `0.0` for
+    /// the best move to `1.0` for the worst. Gaps smaller than one point of the evaluation count
+    /// as one point, so a choice between near-equal moves is never a blunder.
+    pub fn loss(&self) -> f64 {
+        let spread = (self.best_value - self.worst_value).max(1.0);
+        (self.best_value - self.played_value).max(0.0) / spread
+    }
+
+    pub fn is_blunder(&self) -> bool {
+        self.loss() >= BLUNDER_LOSS
+    }
+}
+
+/// Reviews every `(position, move)` pair of a game.
+pub fn analyze(history: &[(Board, Direction)]) -> Vec<MoveReview> {
+    let search = Expectimax::new(DEPTH);
+    history
+        .iter()
+        .filter_map(|(board, played)| {
+            let values = search.evaluate_moves(board);
+            let played_value = values[played.index()]?;
+            let (best, best_value) = Direction::ALL
+                .into_iter()
+                .zip(values)
+                .filter_map(|(d, v)| Some((d, v?)))
+                .fold((*played, played_value), |acc, (d, v)| {
+                    if v > acc.1 { (d, v) } else { acc }
+                });
+            let worst_value = values.into_iter().flatten().fold(played_value, f64::min);
+            Some(MoveReview {
+                board: board.clone(),
+                played: *played,
+                best,
+                played_value,
+                best_value,
+                worst_value,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn review(best_value: f64, played_value: f64, worst_value: f64) -> MoveReview {
+        MoveReview {
+            board: Board::seeded(0),
+            played: Direction::Up,
+            best: Direction::Down,
+            played_value,
+            best_value,
+            worst_value,
+        }
+    }
+
+    #[test]
+    fn loss_is_measured_against_the_other_moves() {
+        assert_eq!(review(100.0, 100.0, 40.0).loss(), 0.0);
+        assert_eq!(review(100.0, 40.0, 40.0).loss(), 1.0);
+        assert_eq!(review(100.0, 85.0, 40.0).loss(), 0.25);
+        // The same gap weighs the same at any level of the evaluation.
+        assert_eq!(review(-20.0, -35.0, -80.0).loss(), 0.25);
+        // Near-equal moves are no blunder.
+        assert!(!review(5000.0, 4999.9, 4999.9).is_blunder());
+    }
+
+    #[test]
+    fn flags_the_worst_move_of_a_game() {
+        let mut board = Board::seeded(0);
+        board
+            .load_text("0,0,0,0/0,0,0,0/0,0,0,0/2,2,4,8 0")
+            .unwrap();
+        let values = Expectimax::new(DEPTH).evaluate_moves(&board);
+        let legal = || {
+            Direction::ALL
+                .into_iter()
+                .filter(|d| values[d.index()].is_some())
+        };
+        let by_value = |d: &Direction| values[d.index()].unwrap();
+        let best = legal()
+            .max_by(|a, b| by_value(a).total_cmp(&by_value(b)))
+            .unwrap();
+        let worst = legal()
+            .min_by(|a, b| by_value(a).total_cmp(&by_value(b)))
+            .unwrap();
+
+        let reviews = analyze(&[(board.clone(), best), (board, worst)]);
+        assert_eq!(reviews[0].loss(), 0.0);
+        assert_eq!(reviews[0].best, best);
+        assert_eq!(reviews[1].loss(), 1.0);
+        assert!(reviews[1].is_blunder());
+    }
+}
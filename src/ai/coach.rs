@@ -0,0 +1,128 @@
+//! Checks a move against the "biggest tile in a corner" strategy.
+
+use crate::board::{Board, Direction};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Warning {
+    /// The biggest tile was in a corner and the move pulled it out.
+    LeftCorner,
+    /// A row that was monotonic is not anymore.
+    BrokeMonotonicity,
+    /// The move left a single empty cell for the spawn when another move kept more room.
+    FilledLastCell,
+}
+
+impl Warning {
+    pub const ALL: [Warning; 3] = [
+        Warning::LeftCorner,
+        Warning::BrokeMonotonicity,
+        Warning::FilledLastCell,
+    ];
+
+    pub fn message(self) -> &'static str {
+        match self {
+            Warning::LeftCorner => "your biggest tile left its corner",
+            Warning::BrokeMonotonicity => "that broke a monotonic row",
+            Warning::FilledLastCell => "that wasted your last free cell",
+        }
+    }
+
+    /// Short label used in the end-of-game summary.
+    pub fn label(self) -> &'static str {
+        match self {
+            Warning::LeftCorner => "corner",
+            Warning::BrokeMonotonicity => "monotonicity",
+            Warning::FilledLastCell => "last cell",
+        }
+    }
+}
+
+/// Returns the warnings earned by playing `direction` from `before`.
+pub fn review(before: &Board, direction: Direction) -> Vec<Warning> {
+    let Some(after) = before.after(direction) else {
+        return Vec::new();
+    };
+    let mut warnings = Vec::new();
+
+    if max_in_corner(before) && !max_in_corner(&after) {
+        warnings.push(Warning::LeftCorner);
+    }
+
+    if monotonic_rows(&after) < monotonic_rows(before) {
+        warnings.push(Warning::BrokeMonotonicity);
+    }
+
+    let room = after.empty_cells().len();
+    let best_room = Direction::ALL
+        .into_iter()
+        .filter_map(|d| before.after(d))
+        .map(|b| b.empty_cells().len())
+        .max()
+        .unwrap_or(0);
+    if room == 1 && best_room > 1 {
+        warnings.push(Warning::FilledLastCell);
+    }
+
+    warnings
+}
+
+fn max_in_corner(board: &Board) -> bool {
+    let max = board.max_tile();
+    board
+        .corners()
+        .iter()
+        .any(|&(r, c)| board.size[r][c] == max)
+}
+
+/// Rows whose tiles, ignoring empty cells, only go up or only go down.
+fn monotonic_rows(board: &Board) -> usize {
+    board.size[..board.dim]
+        .iter()
+        .filter(|row| {
+            let tiles: Vec<u32> = row.iter().copied().filter(|&v| v != 0).collect();
+            tiles.windows(2).all(|w| w[0] <= w[1]) || tiles.windows(2).all(|w| w[0] >= w[1])
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn review_size(size: [[u32; 4]; 4], direction: Direction) -> Vec<Warning> {
+        let mut board = Board::seeded(0);
+        board.size = size;
+        review(&board, direction)
+    }
+
+    #[test]
+    fn each_warning_has_its_move() {
+        let corner = [[64, 0, 0, 4], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0]];
+        assert_eq!(review_size(corner, Direction::Right), [Warning::LeftCorner]);
+        assert_eq!(review_size(corner, Direction::Down), []);
+
+        let rows = [[0, 0, 0, 4], [2, 8, 16, 32], [0, 0, 0, 0], [0, 0, 0, 0]];
+        assert_eq!(
+            review_size(rows, Direction::Up),
+            [Warning::BrokeMonotonicity]
+        );
+
+        let room = [
+            [2, 2, 4, 8],
+            [4, 8, 16, 32],
+            [8, 16, 32, 64],
+            [0, 32, 64, 128],
+        ];
+        assert_eq!(
+            review_size(room, Direction::Down),
+            [Warning::FilledLastCell]
+        );
+        assert_eq!(review_size(room, Direction::Right), []);
+    }
+
+    #[test]
+    fn illegal_moves_earn_nothing() {
+        let stuck = [[2, 4, 2, 4], [4, 2, 4, 2], [2, 4, 2, 4], [4, 2, 4, 2]];
+        assert_eq!(review_size(stuck, Direction::Left), []);
+    }
+}
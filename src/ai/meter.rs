@@ -0,0 +1,165 @@
+//! Background estimate of how the current game will end, from greedy rollouts. Their tiles
+//! spawn at random even in [`crate::board::Difficulty::Evil`] games, whose spawner is far too
+//! slow for hundreds of rollouts.
+
+use crate::ai::strategy::{Greedy, Strategy};
+use crate::board::Board;
+use rand::Rng;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+
+/// Rollouts played between two updates sent to the UI.
+const BATCH: u32 = 8;
+
+/// Rollouts played for one position before the worker goes idle.
+pub const MAX_ROLLOUTS: u32 = 256;
+
+/// Outcome of the rollouts played so far from one position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+    /// Share of rollouts that reached the board's target tile.
+    pub win_probability: f64,
+    /// Average final score of the rollouts.
+    pub expected_score: f64,
+    pub rollouts: u32,
+    /// Which call to [`Meter::update`] this estimate belongs to.
+    generation: u64,
+}
+
+/// Handle to the worker thread. Dropping it stops the worker.
+pub struct Meter {
+    positions: Sender<(u64, Board)>,
+    estimates: Receiver<Estimate>,
+    generation: u64,
+    latest: Option<Estimate>,
+}
+
+impl Default for Meter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Meter {
+    pub fn new() -> Self {
+        let (positions, jobs) = mpsc::channel();
+        let (results, estimates) = mpsc::channel();
+        thread::spawn(move || work(jobs, results));
+        Self {
+            positions,
+            estimates,
+            generation: 0,
+            latest: None,
+        }
+    }
+
+    /// Starts estimating `board`, dropping the work on the previous position.
+    pub fn update(&mut self, board: &Board) {
+        self.generation += 1;
+        self.latest = None;
+        let _ = self.positions.send((self.generation, board.clone()));
+    }
+
+    /// Collects the estimates sent since the last call and returns the most recent one.
+    pub fn poll(&mut self) -> Option<Estimate> {
+        while let Ok(estimate) = self.estimates.try_recv() {
+            if estimate.generation == self.generation {
+                self.latest = Some(estimate);
+            }
+        }
+        self.latest
+    }
+}
+
+fn work(jobs: Receiver<(u64, Board)>, results: Sender<Estimate>) {
+    let mut rng = rand::rng();
+    let mut policy = Greedy;
+
+    'idle: while let Ok(mut job) = jobs.recv() {
+        let (mut wins, mut total, mut rollouts) = (0u32, 0u64, 0u32);
+        while rollouts < MAX_ROLLOUTS {
+            match jobs.try_recv() {
+                Ok(newer) => {
+                    job = newer;
+                    (wins, total, rollouts) = (0, 0, 0);
+                }
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => break 'idle,
+            }
+
+            for _ in 0..BATCH {
+                let mut board = job.1.clone();
+                board.reseed(rng.random());
+                while !board.lost() {
+                    let direction = policy.choose(&board);
+                    board.slide(direction);
+                    board.spawn_one_random();
+                }
+                wins += u32::from(board.won());
+                total += u64::from(board.calculate_score());
+                rollouts += 1;
+            }
+
+            let estimate = Estimate {
+                win_probability: f64::from(wins) / f64::from(rollouts),
+                expected_score: total as f64 / f64::from(rollouts),
+                rollouts,
+                generation: job.0,
+            };
+            if results.send(estimate).is_err() {
+                break 'idle;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn board(size: [[u32; 4]; 4]) -> Board {
+        let mut board = Board::seeded(0);
+        board.size = size;
+        board
+    }
+
+    /// A full board without merges, scoring 46 plus `corner`.
+    fn stuck(corner: u32) -> Board {
+        board([[2, 4, 2, 4], [4, 2, 4, 2], [2, 4, 2, 4], [4, 2, 4, corner]])
+    }
+
+    /// The estimate once every rollout is played.
+    fn settled(meter: &mut Meter) -> Estimate {
+        let start = Instant::now();
+        loop {
+            if let Some(estimate) = meter.poll()
+                && estimate.rollouts == MAX_ROLLOUTS
+            {
+                return estimate;
+            }
+            assert!(start.elapsed() < Duration::from_secs(30), "no estimate");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn finished_games_are_certain() {
+        let mut meter = Meter::new();
+        meter.update(&stuck(32));
+        let lost = settled(&mut meter);
+        assert_eq!((lost.win_probability, lost.expected_score), (0.0, 78.0));
+
+        meter.update(&stuck(2048));
+        let won = settled(&mut meter);
+        assert_eq!((won.win_probability, won.expected_score), (1.0, 2094.0));
+    }
+
+    #[test]
+    fn estimates_follow_the_latest_position() {
+        let mut meter = Meter::new();
+        meter.update(&stuck(32));
+        meter.update(&stuck(64));
+        assert_eq!(settled(&mut meter).expected_score, 110.0);
+    }
+}
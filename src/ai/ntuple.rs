@@ -0,0 +1,368 @@
+//! N-tuple networks trained by temporal-difference learning, after Szubert & Jaśkowski,
+//! "Temporal Difference Learning of N-Tuple Networks for the Game 2048" (2014).
+//!
+//! The network values *afterstates*, the board right after a slide and before the spawn. Each
+//! tuple looks at a few cells, turns their exponents into an index and sums the weights found
+//! there, over all eight rotations and reflections of the board.
+
+use crate::ai::strategy::Strategy;
+use crate::board::{Board, Direction, symmetry_cell};
+use crate::db;
+use color_eyre::eyre::{WrapErr, bail};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Magic bytes at the start of a weight file.
+const MAGIC: &[u8; 8] = b"R2048NT1";
+
+/// Largest exponent a tuple can tell apart, tiles above 32768 share its weights.
+const MAX_EXPONENT: u32 = 15;
+
+/// Most cells a tuple may look at, its table holding `16^6` weights.
+pub const MAX_TUPLE_LEN: usize = 6;
+
+/// Four-cell tuples: the outer and inner rows and three 2×2 squares. Small enough to train in
+/// minutes and to keep in memory anywhere.
+pub const DEFAULT_TUPLES: [&[(usize, usize)]; 5] = [
+    &[(0, 0), (0, 1), (0, 2), (0, 3)],
+    &[(1, 0), (1, 1), (1, 2), (1, 3)],
+    &[(0, 0), (0, 1), (1, 0), (1, 1)],
+    &[(1, 0), (1, 1), (2, 0), (2, 1)],
+    &[(1, 1), (1, 2), (2, 1), (2, 2)],
+];
+
+/// Where `train` saves weights and the `ntuple` strategy loads them from by default.
+pub fn default_path() -> PathBuf {
+    let mut path = db::data_dir();
+    path.push("ntuple.weights");
+    path
+}
+
+#[derive(Debug, Clone)]
+pub struct NTupleNetwork {
+    tuples: Vec<Vec<(usize, usize)>>,
+    /// For every tuple and every symmetry, the flattened cells it reads.
+    features: Vec<(usize, Vec<usize>)>,
+    weights: Vec<Vec<f32>>,
+}
+
+impl Default for NTupleNetwork {
+    fn default() -> Self {
+        Self::new(DEFAULT_TUPLES.iter().map(|t| t.to_vec()).collect())
+            .expect("the default tuples are valid")
+    }
+}
+
+impl NTupleNetwork {
+    /// Creates a network with all weights at zero. Fails on a tuple longer than
+    /// [`MAX_TUPLE_LEN`] or reaching outside the board.
+    pub fn new(tuples: Vec<Vec<(usize, usize)>>) -> color_eyre::Result<Self> {
+        for tuple in &tuples {
+            if tuple.len() > MAX_TUPLE_LEN {
+                bail!(
+                    "a tuple has {} cells, at most {MAX_TUPLE_LEN} are allowed",
+                    tuple.len()
+                );
+            }
+            if tuple.iter().any(|&(r, c)| r >= 4 || c >= 4) {
+                bail!("a tuple reaches outside the board");
+            }
+        }
+
+        let mut features = Vec::new();
+        for (i, tuple) in tuples.iter().enumerate() {
+            for symmetry in 0..8 {
+                let cells = tuple
+                    .iter()
+                    .map(|&cell| {
+                        let (r, c) = symmetry_cell(4, symmetry, cell);
+                        r * 4 + c
+                    })
+                    .collect();
+                features.push((i, cells));
+            }
+        }
+        let weights = tuples
+            .iter()
+            .map(|t| vec![0.0; 1 << (4 * t.len())])
+            .collect();
+
+        Ok(Self {
+            tuples,
+            features,
+            weights,
+        })
+    }
+
+    /// Number of weights in a network with `tuples`.
+    fn weight_count(tuples: &[Vec<(usize, usize)>]) -> usize {
+        tuples.iter().map(|t| 1 << (4 * t.len())).sum()
+    }
+
+    /// Estimated sum of future merges from the afterstate `board`.
+    pub fn value(&self, board: &Board) -> f32 {
+        let cells = exponents(board);
+        self.features
+            .iter()
+            .map(|(tuple, f)| self.weights[*tuple][index(&cells, f)])
+            .sum()
+    }
+
+    /// Moves the value of `board` by `delta`, spread evenly over its features.
+    pub fn update(&mut self, board: &Board, delta: f32) {
+        let cells = exponents(board);
+        let step = delta / self.features.len() as f32;
+        for (tuple, f) in &self.features {
+            self.weights[*tuple][index(&cells, f)] += step;
+        }
+    }
+
+    /// Picks the move maximising the merge reward plus the value of the afterstate, returning it
+    /// along with the afterstate and the reward.
+    pub fn best_move(&self, board: &Board) -> Option<(Direction, Board, f32)> {
+        let mut best: Option<(Direction, Board, f32)> = None;
+        let mut best_value = f32::MIN;
+        for d in Direction::ALL {
+            let Some(after) = board.after(d) else {
+                continue;
+            };
+            let reward = (after.merge_score - board.merge_score) as f32;
+            let value = reward + self.value(&after);
+            if value > best_value {
+                best_value = value;
+                best = Some((d, after, reward));
+            }
+        }
+        best
+    }
+
+    /// Plays one game from `seed` and learns from it with backward TD(0). Returns the final
+    /// board.
+    pub fn learn_episode(&mut self, seed: u64, learning_rate: f32) -> Board {
+        let mut board = Board::seeded(seed);
+        board.init_board();
+
+        let mut path = Vec::new();
+        while let Some((_, after, reward)) = self.best_move(&board) {
+            board = after.clone();
+            board.spawn_one_random();
+            path.push((after, reward));
+        }
+
+        let mut target = 0.0;
+        for (after, reward) in path.iter().rev() {
+            let error = target - self.value(after);
+            self.update(after, learning_rate * error);
+            target = reward + self.value(after);
+        }
+        board
+    }
+
+    pub fn save(&self, path: &Path) -> color_eyre::Result<()> {
+        let file = File::create(path).wrap_err_with(|| format!("failed to create {path:?}"))?;
+        let mut out = BufWriter::new(file);
+        out.write_all(MAGIC)?;
+        out.write_all(&(self.tuples.len() as u32).to_le_bytes())?;
+        for tuple in &self.tuples {
+            out.write_all(&(tuple.len() as u32).to_le_bytes())?;
+            for &(r, c) in tuple {
+                out.write_all(&[r as u8, c as u8])?;
+            }
+        }
+        for weight in self.weights.iter().flatten() {
+            out.write_all(&weight.to_le_bytes())?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> color_eyre::Result<Self> {
+        let file = File::open(path).wrap_err_with(|| format!("failed to open {path:?}"))?;
+        let mut input = BufReader::new(file);
+
+        let mut magic = [0; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            bail!("{path:?} is not an n-tuple weight file");
+        }
+
+        let count = read_u32(&mut input)?;
+        let mut tuples = Vec::new();
+        for _ in 0..count {
+            let len = read_u32(&mut input)? as usize;
+            if len > MAX_TUPLE_LEN {
+                bail!("{path:?} has a tuple of {len} cells, at most {MAX_TUPLE_LEN} are allowed");
+            }
+            let mut tuple = Vec::new();
+            for _ in 0..len {
+                let mut cell = [0; 2];
+                input.read_exact(&mut cell)?;
+                tuple.push((cell[0] as usize, cell[1] as usize));
+            }
+            tuples.push(tuple);
+        }
+
+        // Checked before any table is allocated, so a damaged header can't ask for gigabytes.
+        let mut rest = Vec::new();
+        input.read_to_end(&mut rest)?;
+        let expected = Self::weight_count(&tuples);
+        if rest.len() != expected * 4 {
+            bail!(
+                "{path:?} holds {} bytes of weights, its tuples need {expected} weights",
+                rest.len()
+            );
+        }
+
+        let mut network = Self::new(tuples).wrap_err_with(|| format!("{path:?} is damaged"))?;
+        for (weight, bytes) in network
+            .weights
+            .iter_mut()
+            .flatten()
+            .zip(rest.chunks_exact(4))
+        {
+            *weight = f32::from_le_bytes(bytes.try_into().unwrap());
+        }
+        Ok(network)
+    }
+}
+
+/// Plays with a trained [`NTupleNetwork`].
+pub struct NTuple {
+    pub network: NTupleNetwork,
+}
+
+impl NTuple {
+    pub fn new(network: NTupleNetwork) -> Self {
+        Self { network }
+    }
+
+    /// Loads the weights saved by `r2048 train`.
+    pub fn from_default_weights() -> color_eyre::Result<Self> {
+        let network = NTupleNetwork::load(&default_path())
+            .wrap_err("no trained weights, run `r2048 train` first")?;
+        Ok(Self::new(network))
+    }
+}
+
+impl Strategy for NTuple {
+    fn choose(&mut self, board: &Board) -> Direction {
+        self.network
+            .best_move(board)
+            .map_or(Direction::Up, |(d, _, _)| d)
+    }
+
+    fn name(&self) -> &str {
+        "ntuple"
+    }
+
+    fn evaluations(&mut self, board: &Board) -> Option<[Option<f64>; 4]> {
+        Some(Direction::ALL.map(|d| {
+            let after = board.after(d)?;
+            let reward = (after.merge_score - board.merge_score) as f32;
+            Some(f64::from(reward + self.network.value(&after)))
+        }))
+    }
+}
+
+fn read_u32(input: &mut impl Read) -> color_eyre::Result<u32> {
+    let mut bytes = [0; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn exponents(board: &Board) -> [u32; 16] {
+    let mut cells = [0; 16];
+    for (cell, &v) in cells.iter_mut().zip(board.size.iter().flatten()) {
+        *cell = board.rules.variant.code(v).min(MAX_EXPONENT);
+    }
+    cells
+}
+
+fn index(cells: &[u32; 16], feature: &[usize]) -> usize {
+    feature
+        .iter()
+        .fold(0, |acc, &cell| (acc << 4) | cells[cell] as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn board(size: [[u32; 4]; 4]) -> Board {
+        let mut board = Board::seeded(0);
+        board.size = size;
+        board
+    }
+
+    fn trained(episodes: u64) -> NTupleNetwork {
+        let mut network = NTupleNetwork::default();
+        for seed in 0..episodes {
+            network.learn_episode(seed, 0.1);
+        }
+        network
+    }
+
+    #[test]
+    fn learning_is_repeatable_and_moves_the_weights() {
+        let network = trained(5);
+        assert!(network.weights.iter().flatten().any(|&w| w != 0.0));
+        assert_eq!(network.weights, trained(5).weights);
+
+        // Early positions have a long game of merges ahead of them.
+        let mut start = Board::seeded(0);
+        start.init_board();
+        let (_, after, _) = network.best_move(&start).unwrap();
+        assert!(network.value(&after) > 0.0);
+    }
+
+    #[test]
+    fn updates_pull_the_value_towards_the_target() {
+        let mut network = NTupleNetwork::default();
+        let after = board([[2, 4, 0, 0], [0, 8, 0, 0], [0, 0, 0, 0], [0, 0, 0, 16]]);
+        network.update(&after, 10.0);
+        let raised = network.value(&after);
+        assert!(raised > 0.0);
+        network.update(&after, -2.0 * raised);
+        assert!(network.value(&after) < raised);
+    }
+
+    #[test]
+    fn mirrored_boards_share_their_value() {
+        let network = trained(3);
+        let a = board([[2, 4, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 8]]);
+        let transposed = board([[2, 0, 0, 0], [4, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 8]]);
+        let flipped = board([[0, 0, 4, 2], [0, 0, 0, 0], [0, 0, 0, 0], [8, 0, 0, 0]]);
+        // The same weights, summed in another order.
+        let value = network.value(&a);
+        assert!(value != 0.0);
+        for other in [transposed, flipped] {
+            assert!((network.value(&other) - value).abs() < 1e-3 * value.abs());
+        }
+    }
+
+    #[test]
+    fn weights_round_trip_through_a_file() {
+        let path = std::env::temp_dir().join(format!("r2048-ntuple-{}", std::process::id()));
+        let network = trained(3);
+        network.save(&path).unwrap();
+        let loaded = NTupleNetwork::load(&path);
+
+        let mut bytes = fs::read(&path).unwrap();
+        bytes.pop();
+        fs::write(&path, &bytes).unwrap();
+        let short = NTupleNetwork::load(&path);
+        bytes[0] = b'X';
+        fs::write(&path, &bytes).unwrap();
+        let foreign = NTupleNetwork::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.tuples, network.tuples);
+        assert_eq!(loaded.weights, network.weights);
+        assert!(short.is_err());
+        assert!(foreign.is_err());
+        assert!(NTupleNetwork::load(&path).is_err());
+    }
+}
@@ -0,0 +1,399 @@
+//! Deep expectimax spread over several threads.
+//!
+//! The search runs on a pool of worker threads kept by the strategy from one search to the next.
+//! The children of the root, every spawn after every legal move, are shared out between the
+//! workers, and so are the spawns of the chance nodes close enough to the root to be worth it.
+//! Positions already searched are kept in a transposition table shared by all workers. A
+//! position's value only depends on its cells, their special tiles and walls, and the depth
+//! left, so it does not matter which worker stores it first, and the values of the spawns are
+//! summed in a fixed order: the search returns the same values whatever the number of threads.
+//!
+//! With [`Deep::prune`] on, as it is by default, the search is a depth-limited approximation of
+//! expectimax: past the first [`FULL_WIDTH`] moves only the likeliest tile spawns, and boards
+//! with more than [`OPEN_CELLS`] empty cells are judged by the heuristic alone, as they are in
+//! no danger. Turn it off for the full search, which takes far longer at the default depth.
+
+use crate::ai::strategy::{Strategy, heuristic};
+use crate::board::{Board, Direction, Special};
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Value given to positions with no legal move left.
+const LOST: f64 = -1.0e6;
+
+/// Number of independently locked parts of the transposition table.
+const SHARDS: usize = 64;
+
+/// Moves from the root searched in full when [`Deep::prune`] is on.
+pub const FULL_WIDTH: u32 = 2;
+
+/// Empty cells past which a pruned search stops [`FULL_WIDTH`] moves from the root.
+pub const OPEN_CELLS: usize = 6;
+
+/// Moves left below which a chance node searches its spawns on the current thread, as they are
+/// too quick to be worth sharing out.
+const SHARED_DEPTH: u32 = 3;
+
+/// Depth the `deep` strategy searches to.
+pub const DEFAULT_DEPTH: u32 = 6;
+
+/// Figures about the last search, for tuning the depth and thread count.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchStats {
+    /// Positions evaluated, counting each spawn and each move.
+    pub nodes: u64,
+    /// Positions answered by the transposition table.
+    pub table_hits: u64,
+    /// Entries in the transposition table at the end of the search.
+    pub table_size: usize,
+    pub threads: usize,
+    pub elapsed: Duration,
+}
+
+impl SearchStats {
+    pub fn nodes_per_second(&self) -> f64 {
+        self.nodes as f64 / self.elapsed.as_secs_f64().max(1e-9)
+    }
+
+    /// Share of the nodes answered by the transposition table.
+    pub fn hit_rate(&self) -> f64 {
+        self.table_hits as f64 / self.nodes.max(1) as f64
+    }
+}
+
+impl fmt::Display for SearchStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} nodes in {:.1?} ({:.0} nodes/s, {:.0}% table hits, {} threads)",
+            self.nodes,
+            self.elapsed,
+            self.nodes_per_second(),
+            self.hit_rate() * 100.0,
+            self.threads
+        )
+    }
+}
+
+/// Expectimax searching `depth` moves ahead on `threads` worker threads.
+pub struct Deep {
+    pub depth: u32,
+    pub threads: usize,
+    /// Cut the search down to a depth-limited approximation, see the [module docs](self).
+    pub prune: bool,
+    pool: ThreadPool,
+    /// Figures about the last search made by [`Strategy::choose`].
+    pub last: Option<SearchStats>,
+    /// Figures summed over every search made by [`Strategy::choose`].
+    pub total: SearchStats,
+    pub searches: u64,
+}
+
+impl Default for Deep {
+    fn default() -> Self {
+        Self::new(DEFAULT_DEPTH, default_threads())
+    }
+}
+
+impl Deep {
+    pub fn new(depth: u32, threads: usize) -> Self {
+        let threads = threads.max(1);
+        Self {
+            depth: depth.max(1),
+            threads,
+            prune: true,
+            pool: ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .thread_name(|i| format!("deep-{i}"))
+                .build()
+                .expect("failed to start the search threads"),
+            last: None,
+            total: SearchStats::default(),
+            searches: 0,
+        }
+    }
+
+    /// Expected value of each move in [`Direction::ALL`] order, `None` for moves that don't
+    /// change the board, along with figures about the search.
+    pub fn search(&self, board: &Board) -> ([Option<f64>; 4], SearchStats) {
+        let start = Instant::now();
+        let search = Search::new(self.depth, self.prune);
+
+        // Every spawn after every legal move is a job, weighted by its probability.
+        let mut jobs = Vec::new();
+        let mut moves = [const { None }; 4];
+        for d in Direction::ALL {
+            let Some(after) = board.after(d) else {
+                continue;
+            };
+            let first = jobs.len();
+            let empty = after.spawn_cells();
+            let odds = after.rules.odds();
+            if empty.is_empty() || odds.is_empty() || self.depth == 1 {
+                jobs.push((after, 1.0));
+            } else {
+                let weight = 1.0 / empty.len() as f64;
+                for &(row, col) in &empty {
+                    for &(value, probability) in odds {
+                        let mut next = after.clone();
+                        next.size[row][col] = value;
+                        jobs.push((next, probability * weight));
+                    }
+                }
+            }
+            moves[d.index()] = Some(first..jobs.len());
+        }
+
+        let results: Vec<f64> = self.pool.install(|| {
+            jobs.par_iter()
+                .map(|(job, _)| match self.depth {
+                    1 => heuristic(job),
+                    depth => search.max_node(job, depth - 1),
+                })
+                .collect()
+        });
+
+        // Summed in job order so the float rounding is the same for any thread count.
+        let values = moves.map(|span| Some(span?.map(|i| jobs[i].1 * results[i]).sum()));
+
+        let stats = SearchStats {
+            nodes: search.nodes.load(Ordering::Relaxed) + jobs.len() as u64,
+            table_hits: search.hits.load(Ordering::Relaxed),
+            table_size: search.len(),
+            threads: self.threads,
+            elapsed: start.elapsed(),
+        };
+        (values, stats)
+    }
+}
+
+impl Strategy for Deep {
+    fn choose(&mut self, board: &Board) -> Direction {
+        let values = self.evaluations(board).unwrap_or([None; 4]);
+        // Ties go to the first direction in `Direction::ALL` order.
+        let mut best = (f64::MIN, Direction::Up);
+        for d in Direction::ALL {
+            if let Some(value) = values[d.index()]
+                && value > best.0
+            {
+                best = (value, d);
+            }
+        }
+        best.1
+    }
+
+    fn name(&self) -> &str {
+        "deep"
+    }
+
+    fn evaluations(&mut self, board: &Board) -> Option<[Option<f64>; 4]> {
+        let (values, stats) = self.search(board);
+        self.last = Some(stats);
+        self.total.nodes += stats.nodes;
+        self.total.table_hits += stats.table_hits;
+        self.total.table_size = self.total.table_size.max(stats.table_size);
+        self.total.threads = stats.threads;
+        self.total.elapsed += stats.elapsed;
+        self.searches += 1;
+        Some(values)
+    }
+
+    fn report(&self) -> Option<String> {
+        let last = self.last?;
+        Some(format!(
+            "depth {}, last search {last}, {} searches averaging {:.1?}",
+            self.depth,
+            self.searches,
+            self.total.elapsed / self.searches.max(1) as u32
+        ))
+    }
+}
+
+/// Number of threads the `deep` strategy uses, one per available core.
+pub fn default_threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Everything the value of a node depends on. Stones and wildcards hold the smallest tile in
+/// [`Board::size`], so the special tiles are needed to tell them from plain tiles.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct Key {
+    size: [[u32; 4]; 4],
+    special: [[Option<Special>; 4]; 4],
+    walls: [[bool; 4]; 4],
+    depth: u32,
+}
+
+impl Key {
+    fn new(board: &Board, depth: u32) -> Self {
+        Self {
+            size: board.size,
+            special: board.special,
+            walls: board.walls,
+            depth,
+        }
+    }
+}
+
+/// State shared by the workers of one search.
+struct Search {
+    /// Depth of the root, to tell how far from it a node is.
+    depth: u32,
+    prune: bool,
+    table: Vec<Mutex<HashMap<Key, f64>>>,
+    nodes: AtomicU64,
+    hits: AtomicU64,
+}
+
+impl Search {
+    fn new(depth: u32, prune: bool) -> Self {
+        Self {
+            depth,
+            prune,
+            table: (0..SHARDS).map(|_| Mutex::new(HashMap::new())).collect(),
+            nodes: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.table
+            .iter()
+            .map(|shard| shard.lock().unwrap().len())
+            .sum()
+    }
+
+    fn shard(&self, key: &Key) -> &Mutex<HashMap<Key, f64>> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        &self.table[hasher.finish() as usize % SHARDS]
+    }
+
+    /// Best expected value over the moves from `board`, `depth` moves deep.
+    fn max_node(&self, board: &Board, depth: u32) -> f64 {
+        self.nodes.fetch_add(1, Ordering::Relaxed);
+        if depth == 0 {
+            return heuristic(board);
+        }
+        let key = Key::new(board, depth);
+        let shard = self.shard(&key);
+        if let Some(&value) = shard.lock().unwrap().get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return value;
+        }
+
+        let value = Direction::ALL
+            .into_iter()
+            .filter_map(|d| board.after(d))
+            .map(|next| self.chance_node(&next, depth - 1))
+            .fold(LOST, f64::max);
+        shard.lock().unwrap().insert(key, value);
+        value
+    }
+
+    /// Average value over the spawns on `board`, shared out between the workers when the
+    /// search below is deep enough.
+    fn chance_node(&self, board: &Board, depth: u32) -> f64 {
+        self.nodes.fetch_add(1, Ordering::Relaxed);
+        let empty = board.spawn_cells();
+        let odds = board.rules.odds();
+        if depth == 0 || empty.is_empty() || odds.is_empty() {
+            return heuristic(board);
+        }
+        let deep = self.prune && self.depth - depth > FULL_WIDTH;
+        if deep && empty.len() > OPEN_CELLS {
+            return heuristic(board);
+        }
+        let likeliest = [(odds[0].0, 1.0)];
+        let spawns = if deep { &likeliest[..] } else { odds };
+
+        let children: Vec<(usize, usize, u32, f64)> = empty
+            .iter()
+            .flat_map(|&(row, col)| spawns.iter().map(move |&(v, p)| (row, col, v, p)))
+            .collect();
+        let value = |&(row, col, tile, probability): &(usize, usize, u32, f64)| {
+            let mut next = board.clone();
+            next.size[row][col] = tile;
+            probability * self.max_node(&next, depth)
+        };
+        // Summed in spawn order either way, so sharing them out doesn't change the rounding.
+        let values: Vec<f64> = if depth >= SHARED_DEPTH {
+            children.par_iter().map(value).collect()
+        } else {
+            children.iter().map(value).collect()
+        };
+        values.iter().sum::<f64>() / empty.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{Rules, Specials};
+
+    #[test]
+    fn thread_count_does_not_change_the_values() {
+        let rules = Rules {
+            specials: Specials {
+                wildcard: 10,
+                stone: 10,
+                ..Specials::default()
+            },
+            ..Rules::default()
+        };
+        for text in [
+            "2,4,8,16/0,2,4,8/0,0,2,4/0,0,0,2 0",
+            "0,*,0,o/*,0,o,0/2,2,2,o/0,0,0,0 0",
+            "4,0,2,0/0,o,o,0/0,4,0,0/2,2,2,2 0",
+            "*,0,o,0/2,2,2,4/*,*,0,0/o,0,2,0 0",
+        ] {
+            let mut board = Board::seeded(3);
+            board.rules = rules;
+            board.load_text(text).unwrap();
+            let (one, _) = Deep::new(3, 1).search(&board);
+            for threads in [2, 4, 8, 8, 8] {
+                let (many, _) = Deep::new(3, threads).search(&board);
+                assert_eq!(one, many, "{text} on {threads} threads");
+            }
+        }
+    }
+
+    #[test]
+    fn shared_chance_nodes_keep_the_values() {
+        let mut board = Board::seeded(3);
+        board
+            .load_text("2,4,8,16/0,2,4,8/0,0,2,4/0,0,0,2 0")
+            .unwrap();
+        let (one, _) = Deep::new(SHARED_DEPTH + 2, 1).search(&board);
+        let (many, _) = Deep::new(SHARED_DEPTH + 2, 4).search(&board);
+        assert_eq!(one, many);
+
+        let exact = |threads| {
+            let mut deep = Deep::new(3, threads);
+            deep.prune = false;
+            deep.search(&board).0
+        };
+        assert_eq!(exact(1), exact(4));
+    }
+
+    #[test]
+    fn stones_and_wildcards_are_told_from_plain_tiles() {
+        let mut plain = Board::seeded(0);
+        plain
+            .load_text("2,2,0,0/0,0,0,0/0,0,0,0/0,0,0,0 0")
+            .unwrap();
+        let mut special = plain.clone();
+        special
+            .load_text("*,o,0,0/0,0,0,0/0,0,0,0/0,0,0,0 0")
+            .unwrap();
+        assert_eq!(plain.size, special.size);
+        assert!(Key::new(&plain, 2) != Key::new(&special, 2));
+    }
+}
@@ -0,0 +1,465 @@
+//! Exact solver for 2×2 and 3×3 boards.
+//!
+//! Small boards have few enough positions to evaluate every one of them. The solver walks every
+//! position reachable from the starting boards and stores its value under optimal play, either
+//! the expected sum of future merges or the probability of reaching a target tile. The
+//! resulting [`Table`] is saved to disk and backs the `perfect` strategy.
+
+use crate::ai::strategy::{Expectimax, Strategy};
+use crate::board::{Board, Direction, symmetry_cell};
+use crate::db;
+use color_eyre::eyre::{WrapErr, bail, eyre};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Magic bytes at the start of a table file.
+const MAGIC: &[u8; 8] = b"R2048TB1";
+
+/// Largest side length the solver accepts.
+pub const MAX_SOLVER_DIM: usize = 3;
+
+/// Positions solved between two calls to the progress callback of
+/// [`Table::solve_with_progress`].
+pub const PROGRESS_EVERY: usize = 1_000_000;
+
+/// What the solver maximises.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Objective {
+    /// Expected sum of the merges still to come.
+    Score,
+    /// Probability of getting a tile of at least this value on the board.
+    Reach(u32),
+}
+
+/// Where `solve` saves the table for `dim` and the `perfect` strategy looks for it.
+pub fn default_path(dim: usize) -> PathBuf {
+    let mut path = db::data_dir();
+    path.push(format!("perfect-{dim}x{dim}.table"));
+    path
+}
+
+/// Value of every reachable position of a `dim`×`dim` board, up to symmetry.
+#[derive(Debug, Clone)]
+pub struct Table {
+    pub dim: usize,
+    pub objective: Objective,
+    values: HashMap<u64, f32>,
+}
+
+impl Table {
+    /// Solves every position reachable from the starting boards of a `dim`×`dim` game.
+    pub fn solve(dim: usize, objective: Objective) -> color_eyre::Result<Self> {
+        Self::solve_with_progress(dim, objective, |_| {})
+    }
+
+    /// [`Table::solve`], calling `progress` with the number of positions solved so far every
+    /// [`PROGRESS_EVERY`] positions.
+    pub fn solve_with_progress(
+        dim: usize,
+        objective: Objective,
+        mut progress: impl FnMut(usize),
+    ) -> color_eyre::Result<Self> {
+        if !(2..=MAX_SOLVER_DIM).contains(&dim) {
+            bail!("the solver handles 2×2 and 3×3 boards, not {dim}×{dim}");
+        }
+
+        let mut table = Self {
+            dim,
+            objective,
+            values: HashMap::new(),
+        };
+        let board = Board::sized(dim, 0);
+        let cells: Vec<(usize, usize)> = board.positions().collect();
+        for (i, &a) in cells.iter().enumerate() {
+            for &b in &cells[i + 1..] {
+                let mut start = board.clone();
+                start.size[a.0][a.1] = 2;
+                start.size[b.0][b.1] = 2;
+                table.position(&start, &mut progress);
+            }
+        }
+        Ok(table)
+    }
+
+    /// Number of positions stored.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Value of `board` under optimal play, `None` if it was never reached by the solver.
+    pub fn value(&self, board: &Board) -> Option<f32> {
+        if board.dim != self.dim {
+            return None;
+        }
+        self.values.get(&key(board)).copied()
+    }
+
+    /// Value of playing `direction` from `board` and then playing perfectly, `None` if the move
+    /// is illegal or leads to positions missing from the table.
+    pub fn move_value(&self, board: &Board, direction: Direction) -> Option<f32> {
+        let after = board.after(direction)?;
+        if let Objective::Reach(target) = self.objective
+            && after.max_tile() >= target
+        {
+            return Some(1.0);
+        }
+        let mut total = 0.0;
+        let empty = after.empty_cells();
+        for &(row, col) in &empty {
+            for &(value, probability) in after.rules.odds() {
+                let mut next = after.clone();
+                next.size[row][col] = value;
+                total += probability as f32 * self.value(&next)?;
+            }
+        }
+        Some(self.reward(board, &after) + total / empty.len() as f32)
+    }
+
+    /// Average value of the starting boards, what a perfect player can expect from a new game.
+    pub fn start_value(&self) -> f32 {
+        let board = Board::sized(self.dim, 0);
+        let cells: Vec<(usize, usize)> = board.positions().collect();
+        let (mut total, mut count) = (0.0, 0);
+        for (i, &a) in cells.iter().enumerate() {
+            for &b in &cells[i + 1..] {
+                let mut start = board.clone();
+                start.size[a.0][a.1] = 2;
+                start.size[b.0][b.1] = 2;
+                total += self.value(&start).unwrap_or(0.0);
+                count += 1;
+            }
+        }
+        total / count as f32
+    }
+
+    fn reward(&self, board: &Board, after: &Board) -> f32 {
+        match self.objective {
+            Objective::Score => (after.merge_score - board.merge_score) as f32,
+            Objective::Reach(_) => 0.0,
+        }
+    }
+
+    fn position(&mut self, board: &Board, progress: &mut impl FnMut(usize)) -> f32 {
+        let key = key(board);
+        if let Some(&value) = self.values.get(&key) {
+            return value;
+        }
+
+        let mut best = 0.0f32;
+        for direction in Direction::ALL {
+            let Some(after) = board.after(direction) else {
+                continue;
+            };
+            let value = match self.objective {
+                Objective::Reach(target) if after.max_tile() >= target => 1.0,
+                _ => self.reward(board, &after) + self.chance(&after, progress),
+            };
+            best = best.max(value);
+        }
+
+        self.values.insert(key, best);
+        if self.values.len().is_multiple_of(PROGRESS_EVERY) {
+            progress(self.values.len());
+        }
+        best
+    }
+
+    fn chance(&mut self, after: &Board, progress: &mut impl FnMut(usize)) -> f32 {
+        let empty = after.empty_cells();
+        let mut total = 0.0;
+        for &(row, col) in &empty {
+            for &(value, probability) in after.rules.odds() {
+                let mut next = after.clone();
+                next.size[row][col] = value;
+                next.merge_score = 0;
+                total += probability as f32 * self.position(&next, progress);
+            }
+        }
+        total / empty.len() as f32
+    }
+
+    /// Writes the table with keys sorted and delta-encoded as varints.
+    pub fn save(&self, path: &Path) -> color_eyre::Result<()> {
+        let file = File::create(path).wrap_err_with(|| format!("failed to create {path:?}"))?;
+        let mut out = BufWriter::new(file);
+        out.write_all(MAGIC)?;
+        out.write_all(&[self.dim as u8])?;
+        let (tag, target) = match self.objective {
+            Objective::Score => (0u8, 0u32),
+            Objective::Reach(target) => (1, target),
+        };
+        out.write_all(&[tag])?;
+        out.write_all(&target.to_le_bytes())?;
+        out.write_all(&(self.values.len() as u64).to_le_bytes())?;
+
+        let mut keys: Vec<u64> = self.values.keys().copied().collect();
+        keys.sort_unstable();
+        let mut previous = 0;
+        for key in keys {
+            let mut delta = key - previous;
+            previous = key;
+            loop {
+                let byte = (delta & 0x7f) as u8;
+                delta >>= 7;
+                if delta == 0 {
+                    out.write_all(&[byte])?;
+                    break;
+                }
+                out.write_all(&[byte | 0x80])?;
+            }
+            out.write_all(&self.values[&key].to_le_bytes())?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> color_eyre::Result<Self> {
+        let file = File::open(path).wrap_err_with(|| format!("failed to open {path:?}"))?;
+        let mut input = BufReader::new(file);
+
+        let mut magic = [0; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            bail!("{path:?} is not a solver table");
+        }
+        let mut header = [0; 14];
+        input.read_exact(&mut header)?;
+        let dim = header[0] as usize;
+        if !(2..=MAX_SOLVER_DIM).contains(&dim) {
+            bail!("{path:?} is for a {dim}×{dim} board, the solver handles 2×2 and 3×3 ones");
+        }
+        let target = u32::from_le_bytes(header[2..6].try_into()?);
+        let objective = match header[1] {
+            0 => Objective::Score,
+            1 => Objective::Reach(target),
+            tag => bail!("{path:?} has an unknown objective {tag}"),
+        };
+        let count = u64::from_le_bytes(header[6..14].try_into()?);
+
+        // Every entry takes at least a byte of key and four of value. Checked before the map is
+        // allocated, so a damaged header can't ask for gigabytes.
+        let mut rest = Vec::new();
+        input.read_to_end(&mut rest)?;
+        if count > rest.len() as u64 / 5 {
+            bail!(
+                "{path:?} claims {count} positions but holds only {} bytes of them",
+                rest.len()
+            );
+        }
+
+        let mut values = HashMap::with_capacity(count as usize);
+        let mut bytes = rest.iter().copied();
+        let mut key = 0u64;
+        for _ in 0..count {
+            let (mut delta, mut shift) = (0u64, 0);
+            loop {
+                let byte = bytes.next().ok_or_else(|| eyre!("{path:?} is cut short"))?;
+                if shift > 63 {
+                    bail!("{path:?} has a key longer than 64 bits");
+                }
+                delta |= u64::from(byte & 0x7f) << shift;
+                shift += 7;
+                if byte & 0x80 == 0 {
+                    break;
+                }
+            }
+            key = key
+                .checked_add(delta)
+                .ok_or_else(|| eyre!("{path:?} has a key longer than 64 bits"))?;
+            let mut value = [0; 4];
+            for slot in &mut value {
+                *slot = bytes.next().ok_or_else(|| eyre!("{path:?} is cut short"))?;
+            }
+            values.insert(key, f32::from_le_bytes(value));
+        }
+
+        Ok(Self {
+            dim,
+            objective,
+            values,
+        })
+    }
+}
+
+/// Plays perfectly on boards with a solved table, and falls back to [`Expectimax`] elsewhere.
+pub struct Perfect {
+    tables: HashMap<usize, Option<Table>>,
+    fallback: Expectimax,
+}
+
+impl Default for Perfect {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Perfect {
+    /// Loads tables from [`default_path`] the first time a board of their size is seen.
+    pub fn new() -> Self {
+        Self {
+            tables: HashMap::new(),
+            fallback: Expectimax::new(2),
+        }
+    }
+
+    pub fn with_table(table: Table) -> Self {
+        let mut perfect = Self::new();
+        perfect.tables.insert(table.dim, Some(table));
+        perfect
+    }
+}
+
+impl Perfect {
+    fn table(&mut self, dim: usize) -> Option<&Table> {
+        self.tables
+            .entry(dim)
+            .or_insert_with(|| Table::load(&default_path(dim)).ok())
+            .as_ref()
+    }
+}
+
+impl Strategy for Perfect {
+    fn choose(&mut self, board: &Board) -> Direction {
+        let best = self.evaluations(board).and_then(|values| {
+            Direction::ALL
+                .into_iter()
+                .zip(values)
+                .filter_map(|(d, v)| Some((d, v?)))
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+        });
+        match best {
+            Some((direction, _)) => direction,
+            None => self.fallback.choose(board),
+        }
+    }
+
+    fn name(&self) -> &str {
+        "perfect"
+    }
+
+    /// Exact values from the table, `None` when the board has no table, isn't played by the
+    /// classic rules the table was solved for, or leads to positions the table doesn't cover.
+    fn evaluations(&mut self, board: &Board) -> Option<[Option<f64>; 4]> {
+        if !board.is_classic() {
+            return None;
+        }
+        let table = self.table(board.dim)?;
+        let mut values = [None; 4];
+        for d in Direction::ALL {
+            if board.after(d).is_some() {
+                values[d.index()] = Some(f64::from(table.move_value(board, d)?));
+            }
+        }
+        values.iter().any(Option::is_some).then_some(values)
+    }
+}
+
+/// Packs the [`crate::board::Variant::code`] of the cells in play, four bits each, picking the
+/// smallest packing among the eight rotations and reflections so symmetric positions share one
+/// entry.
+fn key(board: &Board) -> u64 {
+    (0..8)
+        .map(|symmetry| {
+            board.positions().fold(0, |acc, cell| {
+                let (row, col) = symmetry_cell(board.dim, symmetry, cell);
+                let code = board.rules.variant.code(board.size[row][col]);
+                (acc << 4) | u64::from(code)
+            })
+        })
+        .min()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Walls;
+
+    #[test]
+    fn tables_survive_save_and_load() {
+        for objective in [Objective::Score, Objective::Reach(16)] {
+            let table = Table::solve(2, objective).unwrap();
+            assert!(!table.is_empty());
+            let path = std::env::temp_dir().join(format!(
+                "r2048-solver-test-{}-{objective:?}.table",
+                std::process::id()
+            ));
+            table.save(&path).unwrap();
+            let loaded = Table::load(&path);
+            std::fs::remove_file(&path).unwrap();
+            let loaded = loaded.unwrap();
+
+            assert_eq!((loaded.dim, loaded.objective), (table.dim, table.objective));
+            assert_eq!(loaded.values, table.values);
+            assert_eq!(loaded.start_value(), table.start_value());
+        }
+    }
+
+    #[test]
+    fn loading_rejects_damaged_files() {
+        let header = |dim: u8, count: u64| {
+            let mut bytes = MAGIC.to_vec();
+            bytes.extend([dim, 0, 0, 0, 0, 0]);
+            bytes.extend(count.to_le_bytes());
+            bytes
+        };
+        let mut huge = header(3, u64::MAX);
+        huge.extend([1, 0, 0, 0, 0]);
+        let mut cut = header(2, 2);
+        cut.extend([1, 0, 0, 0, 0, 0x81, 0x80, 0x80, 0x80, 1]);
+        let mut long_key = header(2, 1);
+        long_key.extend([0xff; 11]);
+        long_key.extend([0; 4]);
+
+        for (name, bytes) in [
+            ("foreign", b"not a table at all".to_vec()),
+            ("short header", header(2, 0)[..12].to_vec()),
+            ("big board", header(9, 0)),
+            ("huge count", huge),
+            ("cut short", cut),
+            ("long key", long_key),
+        ] {
+            let path = std::env::temp_dir().join(format!(
+                "r2048-solver-test-{}-{name}.table",
+                std::process::id()
+            ));
+            std::fs::write(&path, bytes).unwrap();
+            let loaded = Table::load(&path);
+            std::fs::remove_file(&path).unwrap();
+            assert!(loaded.is_err(), "{name}");
+        }
+    }
+
+    #[test]
+    fn symmetric_positions_share_a_key() {
+        let mut board = Board::sized(3, 0);
+        board.load_text("2,4,0/0,8,0/0,0,16").unwrap();
+        for k in 0..8 {
+            assert_eq!(key(&board.symmetry(k)), key(&board));
+        }
+    }
+
+    #[test]
+    fn perfect_only_plays_classic_rules() {
+        let mut perfect = Perfect::with_table(Table::solve(2, Objective::Score).unwrap());
+        let mut board = Board::sized(2, 0);
+        board.load_text("2,0/0,2").unwrap();
+        assert!(perfect.evaluations(&board).is_some());
+
+        let mut walled = board.clone();
+        walled.rules.walls = Walls::Fixed;
+        assert!(perfect.evaluations(&walled).is_none());
+        let mut wrapping = board.clone();
+        wrapping.rules.wrap = true;
+        assert!(perfect.evaluations(&wrapping).is_none());
+        let mut falling = board.clone();
+        falling.rules.gravity = true;
+        assert!(perfect.evaluations(&falling).is_none());
+    }
+}
@@ -0,0 +1,396 @@
+use crate::ai::ntuple::NTuple;
+use crate::ai::search::Deep;
+use crate::ai::solver::Perfect;
+use crate::board::{Board, Direction};
+use color_eyre::eyre::bail;
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::IndexedRandom};
+
+/// Names accepted by [`by_name`], in the order the TUI cycles through them.
+pub const STRATEGIES: [&str; 8] = [
+    "corner",
+    "greedy",
+    "expectimax",
+    "deep",
+    "ntuple",
+    "perfect",
+    "montecarlo",
+    "random",
+];
+
+/// Value given to positions with no legal move left.
+const LOST: f64 = -1.0e6;
+
+/// A bot that picks moves for a [`Board`].
+///
+/// Implement this trait to plug your own player into the TUI autoplay, the hint key and the
+/// `simulate` subcommand.
+pub trait Strategy {
+    /// Picks the next move. If no move is legal any direction may be returned.
+    fn choose(&mut self, board: &Board) -> Direction;
+
+    /// Short name shown in the UI and in reports.
+    fn name(&self) -> &str {
+        "custom"
+    }
+
+    /// How the strategy rates each move in [`Direction::ALL`] order, `None` for moves that don't
+    /// change the board. Strategies that don't score moves return `None`.
+    fn evaluations(&mut self, _board: &Board) -> Option<[Option<f64>; 4]> {
+        None
+    }
+
+    /// Figures about the work done so far, such as search times, for strategies that keep them.
+    fn report(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Builds one of the built-in strategies from its name. Fails on unknown names and when the
+/// files a strategy plays from, such as the `ntuple` weights, can't be loaded.
+pub fn by_name(name: &str) -> color_eyre::Result<Box<dyn Strategy + Send>> {
+    Ok(match name {
+        "random" => Box::new(Random::new()),
+        "greedy" => Box::new(Greedy),
+        "corner" => Box::new(CornerPriority),
+        "expectimax" => Box::new(Expectimax::new(2)),
+        "deep" => Box::new(Deep::default()),
+        "ntuple" => Box::new(NTuple::from_default_weights()?),
+        "perfect" => Box::new(Perfect::new()),
+        "montecarlo" => Box::new(MonteCarlo::new(20)),
+        _ => bail!(
+            "unknown strategy `{name}`, expected one of {}",
+            STRATEGIES.join(", ")
+        ),
+    })
+}
+
+/// Static evaluation of a position: rewards empty cells, monotonic rows and columns, equal
+/// neighbours and keeping the biggest tile in a corner.
+#[allow(clippy::needless_range_loop)]
+pub fn heuristic(board: &Board) -> f64 {
+    let log = |v: u32| if v == 0 { 0.0 } else { f64::from(v).log2() };
+    let grid = board.size.map(|row| row.map(log));
+
+    let empty = board.empty_cells().len() as f64;
+
+    let mut monotonicity = 0.0;
+    let mut merges = 0.0;
+    let n = board.dim;
+    for i in 0..n {
+        let (mut row_inc, mut row_dec, mut col_inc, mut col_dec) = (0.0, 0.0, 0.0, 0.0);
+        for j in 0..n - 1 {
+            let (a, b) = (grid[i][j], grid[i][j + 1]);
+            if a > b {
+                row_dec += a - b;
+            } else {
+                row_inc += b - a;
+            }
+            let (a, b) = (grid[j][i], grid[j + 1][i]);
+            if a > b {
+                col_dec += a - b;
+            } else {
+                col_inc += b - a;
+            }
+            if board.size[i][j] != 0 && board.size[i][j] == board.size[i][j + 1] {
+                merges += 1.0;
+            }
+            if board.size[j][i] != 0 && board.size[j][i] == board.size[j + 1][i] {
+                merges += 1.0;
+            }
+        }
+        monotonicity -= f64::min(row_inc, row_dec) + f64::min(col_inc, col_dec);
+    }
+
+    let max = board.max_tile();
+    let corner = board
+        .corners()
+        .iter()
+        .any(|&(r, c)| board.size[r][c] == max);
+
+    empty * 270.0
+        + monotonicity * 47.0
+        + merges * 70.0
+        + if corner { log(max) * 100.0 } else { 0.0 }
+}
+
+/// Picks a uniformly random legal move.
+pub struct Random {
+    rng: StdRng,
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Random {
+    pub fn new() -> Self {
+        Self {
+            rng: StdRng::from_rng(&mut rand::rng()),
+        }
+    }
+
+    pub fn seeded(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Strategy for Random {
+    fn choose(&mut self, board: &Board) -> Direction {
+        board
+            .legal_moves()
+            .choose(&mut self.rng)
+            .copied()
+            .unwrap_or(Direction::Up)
+    }
+
+    fn name(&self) -> &str {
+        "random"
+    }
+}
+
+/// Looks one move ahead and takes the move with the best [`heuristic`].
+pub struct Greedy;
+
+impl Strategy for Greedy {
+    fn choose(&mut self, board: &Board) -> Direction {
+        best_by(board, heuristic)
+    }
+
+    fn name(&self) -> &str {
+        "greedy"
+    }
+
+    fn evaluations(&mut self, board: &Board) -> Option<[Option<f64>; 4]> {
+        Some(Direction::ALL.map(|d| board.after(d).map(|next| heuristic(&next))))
+    }
+}
+
+/// Keeps the biggest tile in the bottom-left corner by preferring down, then left, then right.
+pub struct CornerPriority;
+
+impl Strategy for CornerPriority {
+    fn choose(&mut self, board: &Board) -> Direction {
+        [
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+            Direction::Up,
+        ]
+        .into_iter()
+        .find(|&d| board.after(d).is_some())
+        .unwrap_or(Direction::Up)
+    }
+
+    fn name(&self) -> &str {
+        "corner"
+    }
+}
+
+/// Searches `depth` moves ahead, averaging over every possible spawn.
+pub struct Expectimax {
+    pub depth: u32,
+}
+
+impl Expectimax {
+    pub fn new(depth: u32) -> Self {
+        Self {
+            depth: depth.max(1),
+        }
+    }
+
+    /// Expected value of each move in [`Direction::ALL`] order, `None` for moves that don't
+    /// change the board.
+    pub fn evaluate_moves(&self, board: &Board) -> [Option<f64>; 4] {
+        Direction::ALL.map(|d| {
+            board
+                .after(d)
+                .map(|next| Self::chance_node(&next, self.depth - 1))
+        })
+    }
+
+    fn max_node(board: &Board, depth: u32) -> f64 {
+        if depth == 0 {
+            return heuristic(board);
+        }
+        Direction::ALL
+            .into_iter()
+            .filter_map(|d| board.after(d))
+            .map(|next| Self::chance_node(&next, depth - 1))
+            .fold(LOST, f64::max)
+    }
+
+    fn chance_node(board: &Board, depth: u32) -> f64 {
+        let empty = board.spawn_cells();
+        let odds = board.rules.odds();
+        if depth == 0 || empty.is_empty() || odds.is_empty() {
+            return heuristic(board);
+        }
+        let mut total = 0.0;
+        for &(row, col) in &empty {
+            for &(value, probability) in odds {
+                let mut next = board.clone();
+                next.size[row][col] = value;
+                total += probability * Self::max_node(&next, depth);
+            }
+        }
+        total / empty.len() as f64
+    }
+}
+
+impl Strategy for Expectimax {
+    fn choose(&mut self, board: &Board) -> Direction {
+        let depth = self.depth;
+        best_by(board, |next| Self::chance_node(next, depth - 1))
+    }
+
+    fn name(&self) -> &str {
+        "expectimax"
+    }
+
+    fn evaluations(&mut self, board: &Board) -> Option<[Option<f64>; 4]> {
+        Some(self.evaluate_moves(board))
+    }
+}
+
+/// Plays `rollouts` random games after each candidate move and keeps the best average score.
+pub struct MonteCarlo {
+    pub rollouts: u32,
+    rng: StdRng,
+}
+
+impl MonteCarlo {
+    pub fn new(rollouts: u32) -> Self {
+        Self {
+            rollouts: rollouts.max(1),
+            rng: StdRng::from_rng(&mut rand::rng()),
+        }
+    }
+
+    fn rollout(&mut self, mut board: Board) -> f64 {
+        // Don't peek at the real game's upcoming spawns.
+        board.reseed(self.rng.random());
+        loop {
+            board.spawn_one_random();
+            let moves = board.legal_moves();
+            match moves.choose(&mut self.rng) {
+                Some(&d) => {
+                    board.slide(d);
+                }
+                None => return f64::from(board.calculate_score()),
+            }
+        }
+    }
+}
+
+impl Strategy for MonteCarlo {
+    fn choose(&mut self, board: &Board) -> Direction {
+        let mut best = (f64::MIN, Direction::Up);
+        for d in Direction::ALL {
+            let Some(next) = board.after(d) else {
+                continue;
+            };
+            let total: f64 = (0..self.rollouts).map(|_| self.rollout(next.clone())).sum();
+            let average = total / f64::from(self.rollouts);
+            if average > best.0 {
+                best = (average, d);
+            }
+        }
+        best.1
+    }
+
+    fn name(&self) -> &str {
+        "montecarlo"
+    }
+}
+
+/// Returns the legal move whose resulting board scores highest under `eval`.
+fn best_by(board: &Board, mut eval: impl FnMut(&Board) -> f64) -> Direction {
+    let mut best = (f64::MIN, Direction::Up);
+    for d in Direction::ALL {
+        if let Some(next) = board.after(d) {
+            let value = eval(&next);
+            if value > best.0 {
+                best = (value, d);
+            }
+        }
+    }
+    best.1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::simulate::play_game;
+
+    /// A board where sliding down is the only move.
+    fn only_down() -> Board {
+        let mut board = Board::seeded(0);
+        board.size = [[2, 4, 8, 16], [4, 8, 16, 32], [8, 16, 32, 64], [0, 0, 0, 0]];
+        board
+    }
+
+    #[test]
+    fn every_bot_takes_the_only_move() {
+        for name in STRATEGIES {
+            // The n-tuple bot needs trained weights.
+            let Ok(mut strategy) = by_name(name) else {
+                assert_eq!(name, "ntuple");
+                continue;
+            };
+            assert_eq!(strategy.name(), name);
+            assert_eq!(strategy.choose(&only_down()), Direction::Down, "{name}");
+        }
+        assert!(by_name("nobody").is_err());
+    }
+
+    #[test]
+    fn bots_that_rate_moves_skip_the_illegal_ones() {
+        for mut strategy in [
+            Box::new(Greedy) as Box<dyn Strategy>,
+            Box::new(Expectimax::new(2)),
+        ] {
+            let values = strategy.evaluations(&only_down()).unwrap();
+            let legal = values.map(|v| v.is_some());
+            assert_eq!(legal, Direction::ALL.map(|d| d == Direction::Down));
+        }
+        assert_eq!(CornerPriority.evaluations(&only_down()), None);
+    }
+
+    #[test]
+    fn greedy_takes_the_merge() {
+        let mut board = Board::seeded(0);
+        board.size = [[0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0], [2, 2, 4, 8]];
+        let mut greedy = Greedy;
+        assert!(matches!(
+            greedy.choose(&board),
+            Direction::Left | Direction::Right
+        ));
+    }
+
+    /// The trait is all a new bot needs to play.
+    struct Stubborn;
+
+    impl Strategy for Stubborn {
+        fn choose(&mut self, _board: &Board) -> Direction {
+            Direction::Left
+        }
+    }
+
+    #[test]
+    fn custom_bots_plug_in() {
+        let mut stubborn = Stubborn;
+        assert_eq!(stubborn.name(), "custom");
+        let (board, moves) = play_game(&mut stubborn, Board::seeded(1));
+        assert!(moves > 0);
+        assert!(board.after(Direction::Left).is_none());
+
+        let (a, a_moves) = play_game(&mut Random::seeded(3), Board::seeded(4));
+        let (b, b_moves) = play_game(&mut Random::seeded(3), Board::seeded(4));
+        assert_eq!((a.size, a_moves), (b.size, b_moves));
+        assert!(a.lost());
+    }
+}
@@ -0,0 +1,213 @@
+//! Runs a [`Strategy`] on a worker thread, so a slow bot doesn't freeze the screen while it
+//! picks a hint or an autoplay move, or rates the moves of an edited position.
+
+use crate::ai::strategy::{Expectimax, Strategy};
+use crate::board::{Board, Direction};
+use std::iter;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+/// How a bot rates each move in [`Direction::ALL`] order, with the name of the bot that rated
+/// them.
+pub type Ratings = (String, [Option<f64>; 4]);
+
+enum Job {
+    Choose(u64, Board),
+    Rate(u64, Board),
+}
+
+/// Handle to the worker thread. Dropping it stops the worker once its current move is chosen.
+pub struct Thinker {
+    name: String,
+    jobs: Sender<Job>,
+    moves: Receiver<(u64, Direction, Option<String>)>,
+    ratings: Receiver<(u64, Ratings)>,
+    generation: u64,
+    /// [`Strategy::report`] after the last move chosen.
+    report: Option<String>,
+    /// The board asked about, while its move is awaited.
+    asked: Option<Board>,
+    /// The board to rate, while its ratings are awaited.
+    rating: Option<(u64, Board)>,
+}
+
+impl Thinker {
+    pub fn new(mut strategy: Box<dyn Strategy + Send>) -> Self {
+        let name = strategy.name().to_string();
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let (chosen, moves) = mpsc::channel();
+        let (rated, ratings) = mpsc::channel();
+        thread::spawn(move || {
+            while let Ok(first) = queue.recv() {
+                // Only the latest board of each kind matters.
+                let (mut choose, mut rate) = (None, None);
+                for job in iter::once(first).chain(queue.try_iter()) {
+                    match job {
+                        Job::Choose(generation, board) => choose = Some((generation, board)),
+                        Job::Rate(generation, board) => rate = Some((generation, board)),
+                    }
+                }
+                if let Some((generation, board)) = rate {
+                    let ratings = match strategy.evaluations(&board) {
+                        Some(values) => (strategy.name().to_string(), values),
+                        None => (
+                            "expectimax".to_string(),
+                            Expectimax::new(3).evaluate_moves(&board),
+                        ),
+                    };
+                    if rated.send((generation, ratings)).is_err() {
+                        break;
+                    }
+                }
+                if let Some((generation, board)) = choose {
+                    let direction = strategy.choose(&board);
+                    if chosen
+                        .send((generation, direction, strategy.report()))
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            }
+        });
+        Self {
+            name,
+            jobs,
+            moves,
+            ratings,
+            generation: 0,
+            report: None,
+            asked: None,
+            rating: None,
+        }
+    }
+
+    /// Name of the strategy, see [`Strategy::name`].
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Figures about the strategy's work as of the last move it chose, see
+    /// [`Strategy::report`].
+    pub fn report(&self) -> Option<&str> {
+        self.report.as_deref()
+    }
+
+    /// Is a move being chosen?
+    pub fn is_thinking(&self) -> bool {
+        self.asked.is_some()
+    }
+
+    /// Starts choosing a move for `board`, dropping the request before it.
+    pub fn ask(&mut self, board: &Board) {
+        self.generation += 1;
+        self.asked = Some(board.clone());
+        let _ = self.jobs.send(Job::Choose(self.generation, board.clone()));
+    }
+
+    /// The move chosen for the last board asked about, once it is ready. A move arriving after
+    /// `board` moved on from that board is dropped.
+    pub fn poll(&mut self, board: &Board) -> Option<Direction> {
+        let asked = self.asked.as_ref()?;
+        if (asked.size, asked.special) != (board.size, board.special) {
+            self.asked = None;
+            return None;
+        }
+        while let Ok((generation, direction, report)) = self.moves.try_recv() {
+            self.report = report;
+            if generation == self.generation {
+                self.asked = None;
+                return Some(direction);
+            }
+        }
+        None
+    }
+
+    /// Starts rating the moves from `board`, with expectimax when the strategy doesn't rate
+    /// moves, dropping the rating asked for before.
+    pub fn rate(&mut self, board: &Board) {
+        self.generation += 1;
+        self.rating = Some((self.generation, board.clone()));
+        let _ = self.jobs.send(Job::Rate(self.generation, board.clone()));
+    }
+
+    /// Are moves being rated?
+    pub fn is_rating(&self) -> bool {
+        self.rating.is_some()
+    }
+
+    /// The ratings of the last board given to [`Thinker::rate`], once they are ready. They are
+    /// dropped when `board` was edited since.
+    pub fn ratings(&mut self, board: &Board) -> Option<Ratings> {
+        let (asked, rated) = self.rating.as_ref()?;
+        if (rated.size, rated.special, rated.walls, rated.rules)
+            != (board.size, board.special, board.walls, board.rules)
+        {
+            self.rating = None;
+            return None;
+        }
+        let asked = *asked;
+        while let Ok((generation, ratings)) = self.ratings.try_recv() {
+            if generation == asked {
+                self.rating = None;
+                return Some(ratings);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::strategy::by_name;
+    use std::time::{Duration, Instant};
+
+    fn wait<T>(mut ready: impl FnMut() -> Option<T>) -> T {
+        let start = Instant::now();
+        loop {
+            if let Some(value) = ready() {
+                return value;
+            }
+            assert!(
+                start.elapsed() < Duration::from_secs(30),
+                "no answer from the worker"
+            );
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn rates_off_the_calling_thread() {
+        let mut board = Board::seeded(0);
+        board
+            .load_text("2,2,0,0/0,0,0,0/0,0,0,0/0,0,0,4 0")
+            .unwrap();
+
+        let mut greedy = Thinker::new(by_name("greedy").unwrap());
+        greedy.rate(&board);
+        assert!(greedy.is_rating());
+        let (name, values) = wait(|| greedy.ratings(&board));
+        assert_eq!(name, "greedy");
+        assert!(values.iter().all(Option::is_some));
+        assert!(!greedy.is_rating());
+
+        // Bots that don't rate moves leave it to expectimax.
+        let mut random = Thinker::new(by_name("random").unwrap());
+        random.rate(&board);
+        let (name, values) = wait(|| random.ratings(&board));
+        assert_eq!(name, "expectimax");
+        assert_eq!(values, Expectimax::new(3).evaluate_moves(&board));
+    }
+
+    #[test]
+    fn drops_ratings_of_an_edited_board() {
+        let mut board = Board::seeded(0);
+        board.load_text("2,2/0,0 0").unwrap();
+        let mut thinker = Thinker::new(by_name("greedy").unwrap());
+        thinker.rate(&board);
+        board.size[1][1] = 4;
+        assert_eq!(thinker.ratings(&board), None);
+        assert!(!thinker.is_rating());
+    }
+}
@@ -1,13 +1,153 @@
-use crate::db::{self};
-use rand::seq::IteratorRandom;
-use std::cell::RefCell;
+use rand::{
+    Rng, SeedableRng,
+    rngs::StdRng,
+    seq::{IndexedRandom, IteratorRandom},
+};
+use std::str::FromStr;
 
 pub enum State {
     Menu,
     Playing,
     Lost,
     Won,
+    Analysis,
+    Puzzles,
+    Editor,
 }
+
+/// Kind of game being played.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Play until the target tile or until no move is left.
+    Classic,
+    /// Score as much as possible before the clock, in seconds, runs out.
+    TimeAttack(u32),
+    /// Solve the puzzle at this index of the loaded packs.
+    Puzzle(usize),
+    /// Play on from the position set up in the editor, no score is kept.
+    Sandbox,
+}
+
+impl Mode {
+    /// Leaderboard the game's score goes to, `None` for modes that keep no score.
+    pub fn leaderboard(self) -> Option<String> {
+        match self {
+            Mode::Classic => Some("classic".to_string()),
+            Mode::TimeAttack(seconds) => Some(format!("time-attack-{seconds}")),
+            Mode::Puzzle(_) | Mode::Sandbox => None,
+        }
+    }
+}
+
+/// One pass of a [`Rules::gravity`] cascade: the tiles dropped, then the neighbours that met
+/// merged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cascade {
+    /// The cells after the pass.
+    pub size: [[u32; 4]; 4],
+    pub special: [[Option<Special>; 4]; 4],
+    /// Points scored by the merges of the pass, multiplier included.
+    pub points: u32,
+    /// 2 for the first drop after the move, 3 for the next one, and so on.
+    pub multiplier: u32,
+}
+
+/// Clock lengths offered for [`Mode::TimeAttack`], in seconds.
+pub const TIME_LIMITS: [u32; 3] = [60, 180, 300];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Direction::Up => "up",
+            Direction::Down => "down",
+            Direction::Left => "left",
+            Direction::Right => "right",
+        }
+    }
+
+    /// Position of the direction in [`Direction::ALL`].
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn from_index(index: usize) -> Option<Direction> {
+        Direction::ALL.get(index).copied()
+    }
+
+    pub fn arrow(self) -> &'static str {
+        match self {
+            Direction::Up => "↑",
+            Direction::Down => "↓",
+            Direction::Left => "←",
+            Direction::Right => "→",
+        }
+    }
+
+    /// The direction after a quarter turn clockwise, matching [`Board::rotate_clockwise`].
+    pub fn rotated_clockwise(self) -> Direction {
+        match self {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+        }
+    }
+
+    /// The direction reflected across the main diagonal, matching [`Board::transpose`].
+    pub fn transposed(self) -> Direction {
+        match self {
+            Direction::Up => Direction::Left,
+            Direction::Left => Direction::Up,
+            Direction::Down => Direction::Right,
+            Direction::Right => Direction::Down,
+        }
+    }
+
+    /// The direction flipped left to right, matching [`Board::mirror`].
+    pub fn mirrored(self) -> Direction {
+        match self {
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+            other => other,
+        }
+    }
+
+    /// The direction under symmetry `k` of [`Board::symmetry`].
+    pub fn symmetry(self, k: usize) -> Direction {
+        let mut d = self;
+        for _ in 0..k % 4 {
+            d = d.rotated_clockwise();
+        }
+        if k >= 4 { d.mirrored() } else { d }
+    }
+}
+
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Direction::ALL
+            .into_iter()
+            .find(|d| d.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown direction `{s}`"))
+    }
+}
+
 pub trait Move {
     fn move_all_down(&mut self);
     fn move_all_up(&mut self);
@@ -15,228 +155,1669 @@ pub trait Move {
     fn move_all_right(&mut self);
 }
 
-#[derive(Debug)]
+/// Largest supported side length, the classic board.
+pub const MAX_DIM: usize = 4;
+
+/// Win tiles offered in the menu, from quick games to expert ones.
+pub const TARGETS: [u32; 6] = [256, 512, 1024, 2048, 4096, 8192];
+
+/// Which tiles merge, and so which tiles the game is played with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    /// Two equal tiles merge: 2, 4, 8, 16...
+    Classic,
+    /// Two neighbouring Fibonacci numbers merge: 1, 2, 3, 5, 8...
+    Fibonacci,
+    /// Three equal tiles merge into their sum: 3, 9, 27, 81...
+    PowersOfThree,
+    /// As in Threes, a 1 and a 2 make a 3, then two equal tiles merge: 3, 6, 12, 24...
+    Threes,
+}
+
+impl Variant {
+    pub const ALL: [Variant; 4] = [
+        Variant::Classic,
+        Variant::Fibonacci,
+        Variant::PowersOfThree,
+        Variant::Threes,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Variant::Classic => "classic",
+            Variant::Fibonacci => "fibonacci",
+            Variant::PowersOfThree => "powers-of-3",
+            Variant::Threes => "threes",
+        }
+    }
+
+    /// Number of tiles that merge together.
+    pub fn group(self) -> usize {
+        match self {
+            Variant::PowersOfThree => 3,
+            Variant::Classic | Variant::Fibonacci | Variant::Threes => 2,
+        }
+    }
+
+    /// The tile made by merging `tiles`, a run of [`Variant::group`] neighbours in the order
+    /// they slide, or `None` when they don't merge.
+    pub fn merge(self, tiles: &[u32]) -> Option<u32> {
+        match (self, tiles) {
+            (Variant::Classic, &[a, b]) => (a == b).then_some(a + b),
+            (Variant::Fibonacci, &[a, b]) => {
+                let (low, high) = (a.min(b), a.max(b));
+                let (mut x, mut y) = (1u32, 1u32);
+                while x < low || (x == low && y < high) {
+                    (x, y) = (y, x.saturating_add(y));
+                }
+                (x == low && y == high).then_some(a + b)
+            }
+            (Variant::PowersOfThree, &[a, b, c]) => (a == b && b == c).then_some(a + b + c),
+            (Variant::Threes, &[a, b]) => (a + b == 3 || (a == b && a >= 3)).then_some(a + b),
+            _ => None,
+        }
+    }
+
+    /// Every tile of the variant in increasing order, up to the largest that fits in a `u32`.
+    pub fn ladder(self) -> impl Iterator<Item = u32> {
+        let mut state: Option<(u32, u32)> = Some(match self {
+            Variant::Classic => (2, 0),
+            Variant::Fibonacci => (1, 1),
+            Variant::PowersOfThree => (3, 0),
+            Variant::Threes => (1, 0),
+        });
+        std::iter::from_fn(move || {
+            let (tile, previous) = state?;
+            let next = match self {
+                Variant::Classic => tile.checked_mul(2),
+                Variant::Fibonacci => tile.checked_add(previous),
+                Variant::PowersOfThree => tile.checked_mul(3),
+                Variant::Threes if tile < 3 => Some(tile + 1),
+                Variant::Threes => tile.checked_mul(2),
+            };
+            state = next.map(|next| (next, tile));
+            Some(tile)
+        })
+    }
+
+    /// The tile after `tile` on the [`Variant::ladder`].
+    pub fn promote(self, tile: u32) -> u32 {
+        self.ladder().find(|&t| t > tile).unwrap_or(tile)
+    }
+
+    /// Position of `tile` on the [`Variant::ladder`], from 0 for the smallest tile.
+    pub fn rank(self, tile: u32) -> usize {
+        self.ladder().take_while(|&t| t < tile).count()
+    }
+
+    /// Is `tile` on the [`Variant::ladder`]?
+    pub fn is_tile(self, tile: u32) -> bool {
+        self.ladder().take_while(|&t| t <= tile).any(|t| t == tile)
+    }
+
+    /// The tile before `tile` on the [`Variant::ladder`], `0` below the smallest tile.
+    pub fn demote(self, tile: u32) -> u32 {
+        self.ladder().take_while(|&t| t < tile).last().unwrap_or(0)
+    }
+
+    /// Small number standing for `tile` in the encodings of the learned bots: `0` for an empty
+    /// cell, then the tile's [`Variant::rank`] plus one, which is its `log2` in classic games.
+    pub fn code(self, tile: u32) -> u32 {
+        match (self, tile) {
+            (_, 0) => 0,
+            (Variant::Classic, _) => tile.trailing_zeros(),
+            _ => self.rank(tile) as u32 + 1,
+        }
+    }
+
+    /// The two tiles a game starts with.
+    pub fn start(self) -> [u32; 2] {
+        match self {
+            Variant::Classic => [2, 2],
+            Variant::Fibonacci => [1, 1],
+            Variant::PowersOfThree => [3, 3],
+            Variant::Threes => [1, 2],
+        }
+    }
+
+    /// The tiles that can spawn with their probability, used by the search bots.
+    pub fn odds(self, spawns: Spawns) -> &'static [(u32, f64)] {
+        const THIRD: f64 = 1.0 / 3.0;
+        match (self, spawns) {
+            (_, Spawns::Off) => &[],
+            (Variant::Classic, Spawns::Random) => &[(2, 0.9), (4, 0.1)],
+            (Variant::Classic, Spawns::Twos) => &[(2, 1.0)],
+            (Variant::Classic, Spawns::Fours) => &[(4, 1.0)],
+            (Variant::Fibonacci, Spawns::Random) => &[(1, 0.9), (2, 0.1)],
+            (Variant::Fibonacci | Variant::Threes, Spawns::Twos) => &[(1, 1.0)],
+            (Variant::Fibonacci | Variant::Threes, Spawns::Fours) => &[(2, 1.0)],
+            (Variant::PowersOfThree, Spawns::Random) => &[(3, 0.9), (9, 0.1)],
+            (Variant::PowersOfThree, Spawns::Twos) => &[(3, 1.0)],
+            (Variant::PowersOfThree, Spawns::Fours) => &[(9, 1.0)],
+            (Variant::Threes, Spawns::Random) => &[(1, THIRD), (2, THIRD), (3, THIRD)],
+        }
+    }
+
+    /// The random spawns of [`Difficulty::Hard`]: the larger tile more often, and now and then
+    /// the one above it.
+    pub fn hard_odds(self) -> &'static [(u32, f64)] {
+        match self {
+            Variant::Classic => &[(2, 0.7), (4, 0.25), (8, 0.05)],
+            Variant::Fibonacci => &[(1, 0.7), (2, 0.25), (3, 0.05)],
+            Variant::PowersOfThree => &[(3, 0.7), (9, 0.25), (27, 0.05)],
+            Variant::Threes => &[(1, 0.3), (2, 0.3), (3, 0.3), (6, 0.1)],
+        }
+    }
+}
+
+impl FromStr for Variant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Variant::ALL
+            .into_iter()
+            .find(|v| v.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                format!("unknown variant `{s}`, expected classic, fibonacci, powers-of-3 or threes")
+            })
+    }
+}
+
+/// Tiles with a power of their own, spawned as set by [`Rules::specials`]. Wildcards and stones
+/// are worth the variant's smallest tile, bombs and doublers carry the value they spawned with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Special {
+    /// Merges with any tile but a stone, into the tile above it on the ladder.
+    Wildcard,
+    /// Clears its row when it merges.
+    Bomb,
+    /// Doubles the tile it merges into, or takes it one more step up the ladder outside the
+    /// classic game.
+    Doubler,
+    /// Slides like any tile but never merges.
+    Stone,
+}
+
+impl Special {
+    pub const ALL: [Special; 4] = [
+        Special::Wildcard,
+        Special::Bomb,
+        Special::Doubler,
+        Special::Stone,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Special::Wildcard => "wildcard",
+            Special::Bomb => "bomb",
+            Special::Doubler => "doubler",
+            Special::Stone => "stone",
+        }
+    }
+}
+
+/// Percent chance that a spawned tile is special, for each kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Specials {
+    pub wildcard: u8,
+    pub bomb: u8,
+    pub doubler: u8,
+    pub stone: u8,
+}
+
+impl Specials {
+    /// The chances turned on from the menu.
+    pub const SOME: Specials = Specials {
+        wildcard: 3,
+        bomb: 2,
+        doubler: 3,
+        stone: 2,
+    };
+
+    pub fn chance(self, special: Special) -> u8 {
+        match special {
+            Special::Wildcard => self.wildcard,
+            Special::Bomb => self.bomb,
+            Special::Doubler => self.doubler,
+            Special::Stone => self.stone,
+        }
+    }
+
+    /// Can any special tile spawn?
+    pub fn any(self) -> bool {
+        Special::ALL.into_iter().any(|s| self.chance(s) > 0)
+    }
+}
+
+impl FromStr for Specials {
+    type Err = String;
+
+    /// `on` for [`Specials::SOME`], `off`, or chances such as `wildcard=5,bomb=2`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "on" => return Ok(Specials::SOME),
+            "off" => return Ok(Specials::default()),
+            _ => {}
+        }
+        let mut specials = Specials::default();
+        for item in s.split(',') {
+            let (name, chance) = item
+                .split_once('=')
+                .ok_or_else(|| format!("expected `name=percent`, got `{item}`"))?;
+            let chance: u8 = chance
+                .trim()
+                .parse()
+                .map_err(|_| format!("bad chance `{chance}`"))?;
+            let slot = match name.trim() {
+                "wildcard" => &mut specials.wildcard,
+                "bomb" => &mut specials.bomb,
+                "doubler" => &mut specials.doubler,
+                "stone" => &mut specials.stone,
+                other => return Err(format!("unknown special tile `{other}`")),
+            };
+            *slot = chance.min(100);
+        }
+        Ok(specials)
+    }
+}
+
+/// How tiles appear after each move. The tiles themselves depend on the [`Variant`], see
+/// [`Variant::odds`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Spawns {
+    /// A tile drawn from [`Variant::odds`] in a random empty cell, a 2 or one time in ten a 4
+    /// in classic games.
+    Random,
+    /// Always the smaller tile, a 2 in classic games.
+    Twos,
+    /// Always the larger tile, a 4 in classic games.
+    Fours,
+    /// Nothing spawns.
+    Off,
+}
+
+impl Spawns {
+    pub const ALL: [Spawns; 4] = [Spawns::Random, Spawns::Twos, Spawns::Fours, Spawns::Off];
+
+    /// Label for the player, naming the tile that spawns in `variant`, e.g. `1s only` in a
+    /// Fibonacci game.
+    pub fn describe(self, variant: Variant) -> String {
+        match variant.odds(self) {
+            [(tile, _)] => format!("{tile}s only"),
+            _ => self.name().to_string(),
+        }
+    }
+
+    /// Identifier kept with saved positions, the classic game's [`Spawns::describe`].
+    pub fn name(self) -> &'static str {
+        match self {
+            Spawns::Random => "random",
+            Spawns::Twos => "2s only",
+            Spawns::Fours => "4s only",
+            Spawns::Off => "off",
+        }
+    }
+}
+
+/// Where permanent walls stand when a game starts. Tiles can neither enter nor pass a wall.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Walls {
+    Off,
+    /// The same cells every game: the centre of a 3×3 board, two diagonal centre cells of a
+    /// 4×4 one.
+    Fixed,
+    /// As many walls as [`Walls::Fixed`], in cells picked from the game's seed.
+    Random,
+}
+
+impl Walls {
+    pub const ALL: [Walls; 3] = [Walls::Off, Walls::Fixed, Walls::Random];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Walls::Off => "off",
+            Walls::Fixed => "fixed",
+            Walls::Random => "random",
+        }
+    }
+
+    /// The walls of a new `dim`×`dim` board, `rng` places the random ones.
+    pub fn layout(self, dim: usize, rng: &mut StdRng) -> [[bool; 4]; 4] {
+        let mut walls = [[false; 4]; 4];
+        let cells: Vec<(usize, usize)> = match (self, dim) {
+            (Walls::Off, _) | (_, 2) => Vec::new(),
+            (Walls::Fixed, 3) => vec![(1, 1)],
+            (Walls::Fixed, _) => vec![(1, 1), (2, 2)],
+            (Walls::Random, _) => {
+                let cells = (0..dim).flat_map(|row| (0..dim).map(move |col| (row, col)));
+                cells.choose_multiple(rng, dim - 2)
+            }
+        };
+        for (row, col) in cells {
+            walls[row][col] = true;
+        }
+        walls
+    }
+}
+
+impl FromStr for Walls {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Walls::ALL
+            .into_iter()
+            .find(|w| w.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown walls `{s}`, expected off, fixed or random"))
+    }
+}
+
+/// How kind the random spawns are. Only games with [`Spawns::Random`] are affected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difficulty {
+    /// Tiles tend to spawn far from the biggest tile.
+    Easy,
+    /// The classic spawns, in a uniformly random empty cell.
+    Normal,
+    /// Larger tiles spawn more often, see [`Variant::hard_odds`].
+    Hard,
+    /// Every spawn is the cell and tile that hurt the player most, looking
+    /// [`Rules::evil_depth`] moves ahead, see [`crate::ai::adversary::spawn`].
+    Evil,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Normal,
+        Difficulty::Hard,
+        Difficulty::Evil,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+            Difficulty::Evil => "evil",
+        }
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Difficulty::ALL
+            .into_iter()
+            .find(|d| d.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown difficulty `{s}`, expected easy, normal, hard or evil"))
+    }
+}
+
+/// Which way the board turns in a rotating game, every [`Rules::rotate_every`] moves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    Off,
+    Clockwise,
+    Counterclockwise,
+    /// Either way, drawn from the game's seed.
+    Random,
+}
+
+impl Rotation {
+    pub const ALL: [Rotation; 4] = [
+        Rotation::Off,
+        Rotation::Clockwise,
+        Rotation::Counterclockwise,
+        Rotation::Random,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Rotation::Off => "off",
+            Rotation::Clockwise => "clockwise",
+            Rotation::Counterclockwise => "counterclockwise",
+            Rotation::Random => "random",
+        }
+    }
+}
+
+impl FromStr for Rotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Rotation::ALL
+            .into_iter()
+            .find(|r| r.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                format!(
+                    "unknown rotation `{s}`, expected off, clockwise, counterclockwise or random"
+                )
+            })
+    }
+}
+
+/// What a pressure game does when the player lets [`Rules::move_seconds`] go by without moving.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pressure {
+    Off,
+    /// An extra tile spawns.
+    Spawn,
+    /// A random move is played.
+    Move,
+}
+
+impl Pressure {
+    pub const ALL: [Pressure; 3] = [Pressure::Off, Pressure::Spawn, Pressure::Move];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Pressure::Off => "off",
+            Pressure::Spawn => "spawn",
+            Pressure::Move => "move",
+        }
+    }
+}
+
+impl FromStr for Pressure {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Pressure::ALL
+            .into_iter()
+            .find(|p| p.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown pressure `{s}`, expected off, spawn or move"))
+    }
+}
+
+/// Settings of a game that change how it is played or won.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rules {
+    /// Tile that wins the game, rounded up to a tile of the variant by [`Rules::goal`].
+    pub target: u32,
+    pub variant: Variant,
+    pub spawns: Spawns,
+    /// Walls laid out by [`Board::init_board`].
+    pub walls: Walls,
+    /// Do tiles sliding off one edge come back on the opposite one? See [`Board::slide`].
+    pub wrap: bool,
+    /// Do the tiles fall to the bottom after every move, merging as they land? See
+    /// [`Cascade`].
+    pub gravity: bool,
+    pub rotation: Rotation,
+    /// Moves between two turns of the board, see [`Rules::rotation`].
+    pub rotate_every: u32,
+    pub pressure: Pressure,
+    /// Seconds to make a move at the start of a pressure game, see [`Rules::move_seconds`].
+    pub pressure_seconds: u32,
+    pub specials: Specials,
+    pub difficulty: Difficulty,
+    /// Rounds of a spawn and the player's reply the spawner of [`Difficulty::Evil`] looks at,
+    /// 1 or 2.
+    pub evil_depth: u8,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            target: 2048,
+            variant: Variant::Classic,
+            spawns: Spawns::Random,
+            walls: Walls::Off,
+            wrap: false,
+            gravity: false,
+            rotation: Rotation::Off,
+            rotate_every: 10,
+            pressure: Pressure::Off,
+            pressure_seconds: 5,
+            specials: Specials::default(),
+            difficulty: Difficulty::Normal,
+            evil_depth: 1,
+        }
+    }
+}
+
+impl Rules {
+    /// The tile that wins the game: the smallest tile of the variant from [`Rules::target`] up.
+    pub fn goal(&self) -> u32 {
+        self.variant
+            .ladder()
+            .find(|&tile| tile >= self.target)
+            .unwrap_or(u32::MAX)
+    }
+
+    /// The tiles worth celebrating on the way to the goal: the three tiles of the variant below
+    /// it, then the goal itself.
+    pub fn milestones(&self) -> Vec<u32> {
+        let goal = self.goal();
+        let ladder: Vec<u32> = self.variant.ladder().take_while(|&t| t <= goal).collect();
+        ladder[ladder.len().saturating_sub(4)..]
+            .iter()
+            .copied()
+            .filter(|&tile| tile >= 8)
+            .collect()
+    }
+
+    /// Seconds to make a move in a pressure game with `max_tile` on the board: all of
+    /// [`Rules::pressure_seconds`] at first, shrinking with every step up the ladder down to half
+    /// of it once the goal is reached.
+    pub fn move_seconds(&self, max_tile: u32) -> f64 {
+        let goal = self.variant.rank(self.goal()).max(1) as f64;
+        let progress = (self.variant.rank(max_tile) as f64 / goal).min(1.0);
+        f64::from(self.pressure_seconds) * (1.0 - progress / 2.0)
+    }
+
+    /// The tiles that can spawn with their probability.
+    pub fn odds(&self) -> &'static [(u32, f64)] {
+        match (self.difficulty, self.spawns) {
+            (Difficulty::Hard, Spawns::Random) => self.variant.hard_odds(),
+            _ => self.variant.odds(self.spawns),
+        }
+    }
+
+    /// Short description of a `dim`×`dim` board played by these rules, e.g. `4x4 classic to
+    /// 2048, wrap, gravity`, naming everything but the difficulty that changes what a score is
+    /// worth. Scores are only ranked against games with the same signature.
+    pub fn signature(&self, dim: usize) -> String {
+        let mut parts = vec![format!(
+            "{dim}x{dim} {} to {}",
+            self.variant.name(),
+            self.goal()
+        )];
+        if self.spawns != Spawns::Random {
+            parts.push(format!("spawns {}", self.spawns.describe(self.variant)));
+        }
+        if self.walls != Walls::Off {
+            parts.push(format!("walls {}", self.walls.name()));
+        }
+        if self.wrap {
+            parts.push("wrap".to_string());
+        }
+        if self.gravity {
+            parts.push("gravity".to_string());
+        }
+        if self.rotation != Rotation::Off {
+            parts.push(format!(
+                "rotation {} every {}",
+                self.rotation.name(),
+                self.rotate_every
+            ));
+        }
+        if self.pressure != Pressure::Off {
+            parts.push(format!(
+                "pressure {} after {}s",
+                self.pressure.name(),
+                self.pressure_seconds
+            ));
+        }
+        match self.specials {
+            specials if !specials.any() => {}
+            Specials::SOME => parts.push("specials".to_string()),
+            specials => parts.push(format!(
+                "specials wildcard={},bomb={},doubler={},stone={}",
+                specials.wildcard, specials.bomb, specials.doubler, specials.stone
+            )),
+        }
+        if self.difficulty == Difficulty::Evil && self.evil_depth != 1 {
+            parts.push(format!("evil depth {}", self.evil_depth));
+        }
+        parts.join(", ")
+    }
+
+    /// Are these the rules of the classic game, whatever its target and clock? Tables and
+    /// weights learned from classic games only hold under them.
+    pub fn is_classic(&self) -> bool {
+        self.variant == Variant::Classic
+            && self.spawns == Spawns::Random
+            && self.walls == Walls::Off
+            && !self.wrap
+            && !self.gravity
+            && self.rotation == Rotation::Off
+            && !self.specials.any()
+            && self.difficulty == Difficulty::Normal
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Board {
+    /// The cells, only the top-left `dim`×`dim` square is in play.
     pub size: [[u32; 4]; 4],
-    pub db: RefCell<db::Score>,
+    /// Side length of the board, from 2 to [`MAX_DIM`].
+    pub dim: usize,
+    /// Sum of the tiles created by merges this game, the classic 2048 score.
+    pub merge_score: u32,
+    /// Points on top of the tiles: the multipliers of [`Rules::gravity`] cascades and the tiles
+    /// cleared by bombs.
+    pub bonus: u32,
+    /// Source of every spawn, so a seeded board always plays out the same way.
+    pub rng: StdRng,
+    pub rules: Rules,
+    /// Cells taken by a wall, they always hold 0 in [`Board::size`].
+    pub walls: [[bool; 4]; 4],
+    /// Kind of the tile in each cell, `None` for plain tiles and empty cells.
+    pub special: [[Option<Special>; 4]; 4],
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Board {
     pub fn new() -> Self {
+        Self::seeded(rand::rng().random())
+    }
+
+    pub fn seeded(seed: u64) -> Self {
+        Self::sized(MAX_DIM, seed)
+    }
+
+    /// A `dim`×`dim` board, `dim` is clamped to 2..=[`MAX_DIM`].
+    pub fn sized(dim: usize, seed: u64) -> Self {
         Self {
             size: [[0; 4]; 4],
-            db: RefCell::new(db::Score::new().unwrap()),
+            dim: dim.clamp(2, MAX_DIM),
+            merge_score: 0,
+            bonus: 0,
+            rng: StdRng::seed_from_u64(seed),
+            rules: Rules::default(),
+            walls: [[false; 4]; 4],
+            special: [[None; 4]; 4],
         }
     }
 
+    pub fn corners(&self) -> [(usize, usize); 4] {
+        let last = self.dim - 1;
+        [(0, 0), (0, last), (last, 0), (last, last)]
+    }
+
+    /// Every `(row, col)` in play.
+    pub fn positions(&self) -> impl Iterator<Item = (usize, usize)> + use<> {
+        let dim = self.dim;
+        (0..dim).flat_map(move |row| (0..dim).map(move |col| (row, col)))
+    }
+
+    /// Restarts the spawn sequence from `seed`.
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
     #[inline(always)]
     pub fn clear(&mut self) {
         self.size
             .iter_mut()
             .flat_map(|r| r.iter_mut())
             .for_each(|x| *x = 0);
+        self.special = [[None; 4]; 4];
     }
     pub fn init_board(&mut self) {
         self.clear();
+        self.merge_score = 0;
+        self.bonus = 0;
+        self.walls = self.rules.walls.layout(self.dim, &mut self.rng);
 
-        let mut rng = rand::rng();
-
-        let empty_positions: Vec<(usize, usize)> = (0..4)
-            .flat_map(|row| (0..4).map(move |col| (row, col)))
-            .collect();
+        let empty_positions = self.spawn_cells();
 
-        for &(row, col) in empty_positions.iter().choose_multiple(&mut rng, 2).iter() {
-            self.size[*row][*col] = 2;
+        for (&(row, col), tile) in empty_positions
+            .iter()
+            .choose_multiple(&mut self.rng, 2)
+            .into_iter()
+            .zip(self.rules.variant.start())
+        {
+            self.size[row][col] = tile;
         }
     }
 
+    /// Places a tile in a random empty cell, as set by [`Rules::odds`]. [`Difficulty::Evil`]
+    /// spawns are searched for by the caller and placed with [`Board::spawn_with`], here they
+    /// land at random like [`Difficulty::Normal`] ones.
     pub fn spawn_one_random(&mut self) {
-        let mut rng = rand::rng();
-        let mut empty_cells = Vec::new();
+        let odds = self.rules.odds();
+        if odds.is_empty() {
+            return;
+        }
+        let spawn = match (self.rules.difficulty, self.rules.spawns) {
+            (Difficulty::Easy, Spawns::Random) => {
+                let cell = self.far_cell();
+                cell.map(|cell| (cell, self.roll(odds)))
+            }
+            _ => {
+                let cell = self.spawn_cells().into_iter().choose(&mut self.rng);
+                cell.map(|cell| (cell, self.roll(odds)))
+            }
+        };
+        self.place(spawn);
+    }
+
+    /// Places the tile `pick` chooses for this board, given as its cell and value, then rolls
+    /// for a special tile as [`Board::spawn_one_random`] does.
+    pub fn spawn_with(&mut self, pick: impl FnOnce(&Board) -> Option<((usize, usize), u32)>) {
+        let spawn = pick(self);
+        self.place(spawn);
+    }
 
-        for row in 0..4 {
-            for col in 0..4 {
-                if self.size[row][col] == 0 {
-                    empty_cells.push((row, col));
+    fn place(&mut self, spawn: Option<((usize, usize), u32)>) {
+        if let Some(((row, col), value)) = spawn {
+            self.size[row][col] = value;
+
+            let specials = self.rules.specials;
+            if specials.any() {
+                let mut roll = self.rng.random_range(0..100u32);
+                for special in Special::ALL {
+                    let chance = u32::from(specials.chance(special));
+                    if roll < chance {
+                        if matches!(special, Special::Wildcard | Special::Stone) {
+                            self.size[row][col] = self.rules.variant.start()[0];
+                        }
+                        self.special[row][col] = Some(special);
+                        break;
+                    }
+                    roll -= chance;
                 }
             }
         }
+    }
 
-        if let Some((row, col)) = empty_cells.into_iter().choose(&mut rng) {
-            let value = if rand::random::<f32>() < 0.9 { 2 } else { 4 };
-            self.size[row][col] = value;
+    /// Is the board played by the classic rules, with no wall or special tile on it? See
+    /// [`Rules::is_classic`].
+    pub fn is_classic(&self) -> bool {
+        self.rules.is_classic()
+            && self.walls.iter().flatten().all(|&wall| !wall)
+            && self.special.iter().flatten().all(Option::is_none)
+    }
+
+    /// A tile drawn from `odds`.
+    fn roll(&mut self, odds: &[(u32, f64)]) -> u32 {
+        match odds {
+            [(value, _)] => *value,
+            _ => {
+                let roll = self.rng.random::<f32>();
+                let mut total = 0.0;
+                odds.iter()
+                    .find(|&&(_, probability)| {
+                        total += probability as f32;
+                        roll < total
+                    })
+                    .unwrap_or(&odds[odds.len() - 1])
+                    .0
+            }
         }
     }
+
+    /// An empty cell for [`Difficulty::Easy`], more likely the further it is from the biggest
+    /// tile.
+    fn far_cell(&mut self) -> Option<(usize, usize)> {
+        let cells = self.spawn_cells();
+        let max = self.max_tile();
+        let Some((top, left)) = self
+            .positions()
+            .find(|&(row, col)| max > 0 && self.size[row][col] == max)
+        else {
+            return cells.into_iter().choose(&mut self.rng);
+        };
+        cells
+            .choose_weighted(&mut self.rng, |&(row, col)| {
+                let distance = row.abs_diff(top) + col.abs_diff(left);
+                distance * distance
+            })
+            .ok()
+            .copied()
+    }
+
+    /// Is a tile of at least [`Rules::goal`] on the board?
     #[inline(always)]
     pub fn won(&self) -> bool {
-        self.size.iter().flatten().any(|&x| x == 2048)
+        let goal = self.rules.goal();
+        self.size.iter().flatten().any(|&x| x >= goal)
     }
 
+    /// Is no move left? Walls can shut an empty cell off, so a board with room left may still
+    /// be lost.
     pub fn lost(&self) -> bool {
-        let n = self.size.len();
+        Direction::ALL.into_iter().all(|d| self.after(d).is_none())
+    }
+    #[inline(always)]
+    pub fn calculate_score(&self) -> u32 {
+        self.size.iter().flatten().sum::<u32>() + self.bonus
+    }
 
-        if self.size.iter().flatten().any(|&x| x == 0) {
-            return false;
+    #[inline(always)]
+    pub fn max_tile(&self) -> u32 {
+        self.size.iter().flatten().copied().max().unwrap_or(0)
+    }
+
+    /// Cells with neither a tile nor a wall.
+    pub fn empty_cells(&self) -> Vec<(usize, usize)> {
+        self.positions()
+            .filter(|&(row, col)| self.size[row][col] == 0 && !self.walls[row][col])
+            .collect()
+    }
+
+    /// Cells a tile can spawn in: the empty ones, or under [`Rules::gravity`] only those
+    /// resting on the floor, a tile or a wall.
+    pub fn spawn_cells(&self) -> Vec<(usize, usize)> {
+        let mut cells = self.empty_cells();
+        if self.rules.gravity {
+            cells.retain(|&(row, col)| {
+                row + 1 == self.dim || self.size[row + 1][col] != 0 || self.walls[row + 1][col]
+            });
         }
+        cells
+    }
 
-        for i in 0..n {
-            for j in 0..n {
-                let current = self.size[i][j];
-                if j + 1 < n && self.size[i][j + 1] == current {
-                    return false;
-                }
-                if i + 1 < n && self.size[i + 1][j] == current {
-                    return false;
-                }
+    /// Slides the board in `direction`, returning whether any tile moved.
+    ///
+    /// With [`Rules::wrap`] every line is a ring, the cell past its front edge being the one on
+    /// its back edge. Empty cells at the back of a line are then in front of its first tile and
+    /// the tiles slide over the seam into them, and when both ends hold tiles the back ones can
+    /// merge with the front ones over the seam. A lone tile has nothing to stop it and stays at
+    /// the front edge. Walls cut the ring, so the part after a line's last wall slides on into
+    /// the part before its first one.
+    pub fn slide(&mut self, direction: Direction) -> bool {
+        self.slide_steps(direction).is_some()
+    }
+
+    /// [`Board::slide`], also returning the passes of the [`Rules::gravity`] cascade that
+    /// followed, or `None` when nothing moved.
+    pub fn slide_steps(&mut self, direction: Direction) -> Option<Vec<Cascade>> {
+        let before = self.size;
+        let last = self.dim - 1;
+        match direction {
+            Direction::Up => self.slide_lines(|col, k| (k, col)),
+            Direction::Down => self.slide_lines(|col, k| (last - k, col)),
+            Direction::Left => self.slide_lines(|row, k| (row, k)),
+            Direction::Right => self.slide_lines(|row, k| (row, last - k)),
+        }
+        let steps = self.settle();
+        (self.size != before).then_some(steps)
+    }
+
+    /// Turns the board a quarter turn clockwise.
+    pub fn rotate_clockwise(&mut self) {
+        self.transpose();
+        self.mirror();
+    }
+
+    /// Turns the board a quarter turn counterclockwise.
+    pub fn rotate_counterclockwise(&mut self) {
+        self.mirror();
+        self.transpose();
+    }
+
+    /// Turns the board a quarter turn as [`Rules::rotation`] says, returning whether it turned
+    /// clockwise and the passes of the [`Rules::gravity`] cascade that followed, or `None` when
+    /// rotation is off.
+    pub fn rotate(&mut self) -> Option<(bool, Vec<Cascade>)> {
+        let clockwise = match self.rules.rotation {
+            Rotation::Off => return None,
+            Rotation::Clockwise => true,
+            Rotation::Counterclockwise => false,
+            Rotation::Random => self.rng.random_bool(0.5),
+        };
+        if clockwise {
+            self.rotate_clockwise();
+        } else {
+            self.rotate_counterclockwise();
+        }
+        Some((clockwise, self.settle()))
+    }
+
+    /// Reflects the board across its main diagonal.
+    pub fn transpose(&mut self) {
+        for row in 0..self.dim {
+            for col in row + 1..self.dim {
+                let cell = self.size[row][col];
+                self.size[row][col] = self.size[col][row];
+                self.size[col][row] = cell;
+                let wall = self.walls[row][col];
+                self.walls[row][col] = self.walls[col][row];
+                self.walls[col][row] = wall;
+                let special = self.special[row][col];
+                self.special[row][col] = self.special[col][row];
+                self.special[col][row] = special;
             }
         }
+    }
 
-        true
+    /// Flips the board left to right.
+    pub fn mirror(&mut self) {
+        let dim = self.dim;
+        for row in self.size[..dim].iter_mut() {
+            row[..dim].reverse();
+        }
+        for row in self.walls[..dim].iter_mut() {
+            row[..dim].reverse();
+        }
+        for row in self.special[..dim].iter_mut() {
+            row[..dim].reverse();
+        }
     }
-    #[inline(always)]
-    pub fn calculate_score(&self) -> u32 {
-        let val: u32 = self.size.iter().flatten().sum();
-        if val > self.db.borrow().get_score().unwrap() {
-            self.db.borrow_mut().update_score(val).unwrap()
+
+    /// One of the eight rotations and reflections of the board: `k % 4` quarter turns
+    /// clockwise, then a left-right flip when `k >= 4`. See [`symmetry_cell`].
+    pub fn symmetry(&self, k: usize) -> Board {
+        let mut board = self.clone();
+        for (row, col) in self.positions() {
+            let (r, c) = symmetry_cell(self.dim, k, (row, col));
+            board.size[row][col] = self.size[r][c];
+            board.walls[row][col] = self.walls[r][c];
+            board.special[row][col] = self.special[r][c];
         }
-        val
+        board
     }
-}
 
-impl Move for Board {
-    fn move_all_down(&mut self) {
-        for i in 0..self.size.len() {
-            let mut merged = [false; 4];
-            let mut stack: Vec<(u32, usize)> = Vec::new();
-            let mut j = 0;
-            while j < self.size[0].len() {
-                stack.push((self.size[j][i], j));
-                j += 1;
-            }
-            while let Some(value) = stack.pop() {
-                let mut index = value.1;
-                while index + 1 < j {
-                    let val: u32 = self.size[index][i];
-
-                    match self.size[index + 1][i] {
-                        0 => {
-                            self.size[index + 1][i] = val;
-                            self.size[index][i] = 0;
-                            index += 1;
-                        }
+    /// Returns the board after sliding in `direction`, or `None` if nothing would move.
+    pub fn after(&self, direction: Direction) -> Option<Board> {
+        let mut next = self.clone();
+        next.slide(direction).then_some(next)
+    }
 
-                        other if other == val && !merged[index + 1] => {
-                            self.size[index + 1][i] += other;
-                            self.size[index][i] = 0;
-                            merged[index + 1] = true;
-                            break;
-                        }
+    /// Writes the cells and merge score as text, e.g. `2,0,0,0/0,4,#,0/0,0,0,0/0,0,0,0 12`,
+    /// with `#` for a wall, `*` for a wildcard, `o` for a stone and a `b` or `d` after the value
+    /// of a bomb or a doubler. A [`Board::bonus`] follows the merge score when there is one.
+    /// The spawn sequence is not included.
+    pub fn to_text(&self) -> String {
+        let rows: Vec<String> = (0..self.dim)
+            .map(|row| {
+                (0..self.dim)
+                    .map(|col| match (self.walls[row][col], self.special[row][col]) {
+                        (true, _) => "#".to_string(),
+                        (false, Some(Special::Wildcard)) => "*".to_string(),
+                        (false, Some(Special::Stone)) => "o".to_string(),
+                        (false, Some(Special::Bomb)) => format!("{}b", self.size[row][col]),
+                        (false, Some(Special::Doubler)) => format!("{}d", self.size[row][col]),
+                        (false, None) => self.size[row][col].to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(",")
+            })
+            .collect();
+        match self.bonus {
+            0 => format!("{} {}", rows.join("/"), self.merge_score),
+            bonus => format!("{} {} {bonus}", rows.join("/"), self.merge_score),
+        }
+    }
+
+    /// Restores the cells, size, merge score and bonus written by [`Board::to_text`], keeping
+    /// this board's spawn sequence. Tiles must be on the ladder of this board's variant.
+    pub fn load_text(&mut self, text: &str) -> Result<(), String> {
+        let mut parts = text.split_whitespace();
+        let grid = parts.next().ok_or("empty board")?;
+        let merge_score = match parts.next() {
+            Some(score) => score.parse().map_err(|_| format!("bad score `{score}`"))?,
+            None => 0,
+        };
+        let bonus = match parts.next() {
+            Some(bonus) => bonus.parse().map_err(|_| format!("bad bonus `{bonus}`"))?,
+            None => 0,
+        };
 
-                        _ => break,
+        let mut size = [[0; 4]; 4];
+        let mut walls = [[false; 4]; 4];
+        let mut special = [[None; 4]; 4];
+        let rows: Vec<&str> = grid.split('/').collect();
+        let dim = rows.len();
+        if !(2..=MAX_DIM).contains(&dim) {
+            return Err(format!("expected 2 to {MAX_DIM} rows, found {dim}"));
+        }
+        for (row, line) in rows.into_iter().enumerate() {
+            let cells: Vec<&str> = line.split(',').collect();
+            if cells.len() != dim {
+                return Err(format!("expected {dim} cells in `{line}`"));
+            }
+            for (col, value) in cells.into_iter().enumerate() {
+                let bad = || format!("bad cell `{value}`");
+                let smallest = self.rules.variant.start()[0];
+                (size[row][col], special[row][col]) = match value {
+                    "#" => {
+                        walls[row][col] = true;
+                        (0, None)
                     }
+                    "*" => (smallest, Some(Special::Wildcard)),
+                    "o" => (smallest, Some(Special::Stone)),
+                    _ => match value.strip_suffix('b').or(value.strip_suffix('d')) {
+                        Some(tile) => {
+                            let kind = match value.ends_with('b') {
+                                true => Special::Bomb,
+                                false => Special::Doubler,
+                            };
+                            (tile.parse().map_err(|_| bad())?, Some(kind))
+                        }
+                        None => (value.parse().map_err(|_| bad())?, None),
+                    },
+                };
+                let variant = self.rules.variant;
+                if size[row][col] != 0 && !variant.is_tile(size[row][col]) {
+                    return Err(format!("`{value}` is not a {} tile", variant.name()));
                 }
             }
         }
+
+        self.size = size;
+        self.walls = walls;
+        self.special = special;
+        self.dim = dim;
+        self.merge_score = merge_score;
+        self.bonus = bonus;
+        Ok(())
     }
 
-    fn move_all_up(&mut self) {
-        for i in 0..self.size.len() {
-            let mut merged = [false; 4];
-            let mut stack: Vec<(u32, usize)> = Vec::new();
-            let mut j = self.size[0].len() - 1;
-            while j > 0 {
-                stack.push((self.size[j][i], j));
-                j -= 1;
-            }
-            while let Some(value) = stack.pop() {
-                let mut index = value.1;
-                while index > 0 {
-                    let val: u32 = self.size[index][i];
-                    match self.size[index - 1][i] {
-                        0 => {
-                            self.size[index - 1][i] = val;
-                            self.size[index][i] = 0;
-                            index -= 1;
-                        }
+    pub fn legal_moves(&self) -> Vec<Direction> {
+        Direction::ALL
+            .into_iter()
+            .filter(|&d| self.after(d).is_some())
+            .collect()
+    }
+}
 
-                        other if other == val && !merged[index - 1] => {
-                            self.size[index - 1][i] += other;
-                            self.size[index][i] = 0;
-                            merged[index - 1] = true;
-                            break;
-                        }
+impl Board {
+    /// Slides every line of the board towards its front, `cell(line, k)` being the `k`-th cell
+    /// of a line counting from the front.
+    fn slide_lines(&mut self, cell: impl Fn(usize, usize) -> (usize, usize)) {
+        let mut cells = [(0, 0); MAX_DIM];
+        // Rows hit by a bomb, cleared once every line has slid.
+        let mut bombed = [false; MAX_DIM];
+        for line in 0..self.dim {
+            for (k, slot) in cells[..self.dim].iter_mut().enumerate() {
+                *slot = cell(line, k);
+            }
+            if self.rules.wrap {
+                let shift = self.seam_shift(&cells[..self.dim]);
+                cells[..self.dim].rotate_right(shift);
+            }
+            let walls = self.walls;
+            for segment in cells[..self.dim].split(|&(row, col)| walls[row][col]) {
+                self.slide_segment(segment, &mut bombed);
+            }
+        }
+
+        self.clear_rows(&bombed);
+    }
+
+    /// Empties the rows marked in `bombed`. The tiles cleared keep counting towards the score,
+    /// through [`Board::bonus`].
+    fn clear_rows(&mut self, bombed: &[bool; MAX_DIM]) {
+        for row in (0..self.dim).filter(|&row| bombed[row]) {
+            self.bonus += self.size[row].iter().sum::<u32>();
+            self.size[row] = [0; 4];
+            self.special[row] = [None; 4];
+        }
+    }
+
+    /// Number of cells at the back of a wrapping `line` that come round in front of it before
+    /// it slides: those after its last wall, the empty ones, or the tiles that merge with the
+    /// front ones over the seam.
+    fn seam_shift(&self, line: &[(usize, usize)]) -> usize {
+        let n = line.len();
+        if let Some(last_wall) = line.iter().rposition(|&(row, col)| self.walls[row][col]) {
+            return n - 1 - last_wall;
+        }
+        let value = |k: usize| {
+            let (row, col) = line[k];
+            self.size[row][col]
+        };
+        let tile = |k: usize| {
+            let (row, col) = line[k];
+            (self.size[row][col], self.special[row][col])
+        };
+        if (0..n).filter(|&k| value(k) != 0).count() < 2 {
+            return 0;
+        }
+        let empty_back = (0..n).rev().take_while(|&k| value(k) == 0).count();
+        if empty_back > 0 || value(0) == 0 {
+            return empty_back;
+        }
 
-                        _ => break,
+        let variant = self.rules.variant;
+        let group = variant.group();
+        (1..group)
+            .find(|&shift| {
+                let mut run = [(0, None); MAX_DIM];
+                let mut len = 0;
+                for k in (n - shift..n).chain(0..n - shift) {
+                    if value(k) != 0 && len < group {
+                        run[len] = tile(k);
+                        len += 1;
                     }
                 }
+                (n - shift..n).all(|k| value(k) != 0) && merge_run(variant, &run[..len]).is_some()
+            })
+            .unwrap_or(0)
+    }
+
+    /// Packs the tiles of `segment` towards its first cell, merging neighbours as
+    /// [`merge_run`] allows, from the front. A tile made by a merge doesn't merge again in the
+    /// same move. Marks in `bombed` the rows where a bomb merged.
+    fn slide_segment(&mut self, segment: &[(usize, usize)], bombed: &mut [bool; MAX_DIM]) {
+        let mut tiles = [(0, None); MAX_DIM];
+        let mut count = 0;
+        for &(row, col) in segment {
+            if self.size[row][col] != 0 {
+                tiles[count] = (self.size[row][col], self.special[row][col]);
+                count += 1;
             }
         }
-    }
-    fn move_all_right(&mut self) {
-        for i in 0..self.size.len() {
-            let mut merged = [false; 4];
-            let mut stack: Vec<(u32, usize)> = Vec::new();
-            let mut j = 0;
-            while j < self.size[0].len() {
-                stack.push((self.size[i][j], j));
-                j += 1;
-            }
-            while let Some(value) = stack.pop() {
-                let mut index = value.1;
-                while index + 1 < j {
-                    let val = self.size[i][index];
-
-                    match self.size[i][index + 1] {
-                        0 => {
-                            self.size[i][index + 1] = val;
-                            self.size[i][index] = 0;
-                            index += 1;
-                        }
-                        other if other == val && !merged[index + 1] => {
-                            self.size[i][index + 1] += other;
-                            self.size[i][index] = 0;
-                            merged[index + 1] = true;
-                            break;
-                        }
 
-                        _ => break,
+        let variant = self.rules.variant;
+        let group = variant.group();
+        let mut packed = [(0, None); MAX_DIM];
+        let (mut i, mut len) = (0, 0);
+        while i < count {
+            let run = tiles[i..count].get(..group).unwrap_or_default();
+            match merge_run(variant, run) {
+                Some(tile) => {
+                    if run.iter().any(|&(_, s)| s == Some(Special::Bomb)) {
+                        bombed[segment[len].0] = true;
                     }
+                    packed[len] = (tile, None);
+                    self.merge_score += tile;
+                    i += group;
+                }
+                None => {
+                    packed[len] = tiles[i];
+                    i += 1;
                 }
             }
+            len += 1;
+        }
+        for (&(row, col), &(value, special)) in segment.iter().zip(&packed) {
+            self.size[row][col] = value;
+            self.special[row][col] = special;
         }
     }
 
-    fn move_all_left(&mut self) {
-        for i in 0..self.size.len() {
-            let mut merged = [false; 4];
-            let mut stack: Vec<(u32, usize)> = Vec::new();
-            let mut j = self.size[0].len() - 1;
-            while j > 0 {
-                stack.push((self.size[i][j], j));
-                j -= 1;
-            }
-            while let Some(value) = stack.pop() {
-                let mut index = value.1;
-                while index > 0 {
-                    let val = self.size[i][index];
-
-                    match self.size[i][index - 1] {
-                        0 => {
-                            self.size[i][index - 1] = val;
-                            self.size[i][index] = 0;
-                            index -= 1;
-                        }
-                        other if other == val && !merged[index - 1] => {
-                            self.size[i][index - 1] += other;
-                            self.size[i][index] = 0;
-                            merged[index - 1] = true;
-                            break;
-                        }
+    /// Under [`Rules::gravity`], drops every tile, merges the neighbours that meet and repeats
+    /// until nothing moves. Merges made after the `n`-th drop score `n + 1` times their tile.
+    /// Returns the passes that changed the board.
+    fn settle(&mut self) -> Vec<Cascade> {
+        let mut steps = Vec::new();
+        if !self.rules.gravity {
+            return steps;
+        }
+        for multiplier in 2.. {
+            let before = self.size;
+            self.fall();
+            let points = self.merge_neighbours(multiplier);
+            if self.size == before {
+                break;
+            }
+            steps.push(Cascade {
+                size: self.size,
+                special: self.special,
+                points,
+                multiplier,
+            });
+            if points == 0 {
+                break;
+            }
+        }
+        steps
+    }
 
-                        _ => break,
+    /// Drops every tile to the bottom of its column, or onto the wall under it.
+    fn fall(&mut self) {
+        let last = self.dim - 1;
+        let mut cells = [(0, 0); MAX_DIM];
+        for col in 0..self.dim {
+            for (k, slot) in cells[..self.dim].iter_mut().enumerate() {
+                *slot = (last - k, col);
+            }
+            let walls = self.walls;
+            for segment in cells[..self.dim].split(|&(row, col)| walls[row][col]) {
+                let mut tiles = [(0, None); MAX_DIM];
+                let mut count = 0;
+                for &(row, col) in segment {
+                    if self.size[row][col] != 0 {
+                        tiles[count] = (self.size[row][col], self.special[row][col]);
+                        count += 1;
                     }
                 }
+                for (&(row, col), &(value, special)) in segment.iter().zip(&tiles) {
+                    self.size[row][col] = value;
+                    self.special[row][col] = special;
+                }
+            }
+        }
+    }
+
+    /// Merges the runs of [`Variant::group`] neighbouring tiles that [`merge_run`] allows, up a
+    /// column or along a row, into their lowest or leftmost cell. Scans from the bottom row and
+    /// merges each tile at most once. Returns the points scored, `multiplier` times each tile.
+    fn merge_neighbours(&mut self, multiplier: u32) -> u32 {
+        let variant = self.rules.variant;
+        let group = variant.group();
+        let mut merged = [[false; 4]; 4];
+        let mut bombed = [false; MAX_DIM];
+        let mut points = 0;
+        for row in (0..self.dim).rev() {
+            for col in 0..self.dim {
+                let up = (row + 1 >= group).then_some([(0, 0), (1, 0), (2, 0)]);
+                let right = (col + group <= self.dim).then_some([(0, 0), (0, 1), (0, 2)]);
+                for steps in [up, right].into_iter().flatten() {
+                    let mut cells = [(0, 0); MAX_DIM];
+                    let mut run = [(0, None); MAX_DIM];
+                    for (k, &(dr, dc)) in steps[..group].iter().enumerate() {
+                        let (r, c) = (row - dr, col + dc);
+                        cells[k] = (r, c);
+                        run[k] = (self.size[r][c], self.special[r][c]);
+                    }
+                    let cells = &cells[..group];
+                    if cells
+                        .iter()
+                        .any(|&(r, c)| merged[r][c] || self.size[r][c] == 0)
+                    {
+                        continue;
+                    }
+                    let Some(tile) = merge_run(variant, &run[..group]) else {
+                        continue;
+                    };
+                    if run[..group].iter().any(|&(_, s)| s == Some(Special::Bomb)) {
+                        bombed[row] = true;
+                    }
+                    for &(r, c) in cells {
+                        self.size[r][c] = 0;
+                        self.special[r][c] = None;
+                        merged[r][c] = true;
+                    }
+                    self.size[row][col] = tile;
+                    points += tile * multiplier;
+                    break;
+                }
+            }
+        }
+
+        self.clear_rows(&bombed);
+        self.merge_score += points;
+        self.bonus += points - points / multiplier;
+        points
+    }
+}
+
+/// The tile made by merging `run`, a run of [`Variant::group`] neighbours in the order they
+/// slide, or `None` when they don't merge. Stones never merge, and wildcards stand for any
+/// tile: the others must all be equal and the run makes the tile above them on the ladder. A
+/// doubler in the run takes the tile one more step up.
+fn merge_run(variant: Variant, run: &[(u32, Option<Special>)]) -> Option<u32> {
+    let tile = merge_plain(variant, run)?;
+    Some(
+        match run.iter().any(|&(_, s)| s == Some(Special::Doubler)) {
+            true => variant.promote(tile),
+            false => tile,
+        },
+    )
+}
+
+/// [`merge_run`] before any doubler.
+fn merge_plain(variant: Variant, run: &[(u32, Option<Special>)]) -> Option<u32> {
+    if run.len() != variant.group() || run.iter().any(|&(_, s)| s == Some(Special::Stone)) {
+        return None;
+    }
+    let mut values = [0; MAX_DIM];
+    for (value, &(tile, _)) in values.iter_mut().zip(run) {
+        *value = tile;
+    }
+    if !run.iter().any(|&(_, s)| s == Some(Special::Wildcard)) {
+        return variant.merge(&values[..run.len()]);
+    }
+    let mut others = run
+        .iter()
+        .filter(|&&(_, s)| s != Some(Special::Wildcard))
+        .map(|&(tile, _)| tile);
+    match others.next() {
+        Some(first) => others.all(|t| t == first).then(|| variant.promote(first)),
+        None => Some(variant.promote(run[0].0)),
+    }
+}
+
+impl Move for Board {
+    fn move_all_down(&mut self) {
+        self.slide(Direction::Down);
+    }
+
+    fn move_all_up(&mut self) {
+        self.slide(Direction::Up);
+    }
+
+    fn move_all_right(&mut self) {
+        self.slide(Direction::Right);
+    }
+
+    fn move_all_left(&mut self) {
+        self.slide(Direction::Left);
+    }
+}
+
+/// The cell of a `dim`×`dim` board that ends up at `cell` under symmetry `k` of
+/// [`Board::symmetry`], the one place the eight symmetries are worked out.
+pub fn symmetry_cell(dim: usize, k: usize, cell: (usize, usize)) -> (usize, usize) {
+    let last = dim - 1;
+    let (mut row, mut col) = cell;
+    if k >= 4 {
+        col = last - col;
+    }
+    for _ in 0..k % 4 {
+        (row, col) = (last - col, row);
+    }
+    (row, col)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// A board of `rules` holding the cells of `text`, in the format of [`Board::to_text`].
+    fn board(text: &str, rules: Rules) -> Board {
+        let mut board = Board::seeded(0);
+        board.rules = rules;
+        board.load_text(text).unwrap();
+        board
+    }
+
+    #[test]
+    fn variants_merge_their_own_tiles() {
+        assert_eq!(Variant::Classic.merge(&[4, 4]), Some(8));
+        assert_eq!(Variant::Classic.merge(&[2, 4]), None);
+        assert_eq!(Variant::Fibonacci.merge(&[1, 1]), Some(2));
+        assert_eq!(Variant::Fibonacci.merge(&[5, 3]), Some(8));
+        assert_eq!(Variant::Fibonacci.merge(&[2, 5]), None);
+        assert_eq!(Variant::Fibonacci.merge(&[3, 3]), None);
+        assert_eq!(Variant::PowersOfThree.merge(&[9, 9, 9]), Some(27));
+        assert_eq!(Variant::PowersOfThree.merge(&[9, 9]), None);
+        assert_eq!(Variant::Threes.merge(&[2, 1]), Some(3));
+        assert_eq!(Variant::Threes.merge(&[1, 1]), None);
+        assert_eq!(Variant::Threes.merge(&[2, 2]), None);
+        assert_eq!(Variant::Threes.merge(&[6, 6]), Some(12));
+    }
+
+    #[test]
+    fn ladders_climb_by_merges() {
+        let start = |variant: Variant| variant.ladder().take(5).collect::<Vec<_>>();
+        assert_eq!(start(Variant::Classic), [2, 4, 8, 16, 32]);
+        assert_eq!(start(Variant::Fibonacci), [1, 2, 3, 5, 8]);
+        assert_eq!(start(Variant::PowersOfThree), [3, 9, 27, 81, 243]);
+        assert_eq!(start(Variant::Threes), [1, 2, 3, 6, 12]);
+        assert_eq!(Variant::Classic.ladder().last(), Some(1 << 31));
+
+        for variant in Variant::ALL {
+            let ladder: Vec<u32> = variant.ladder().collect();
+            assert!(ladder.windows(2).all(|w| w[0] < w[1]), "{}", variant.name());
+            // Past the starting tiles every rung is made by merging the ones below it.
+            for &tile in ladder.iter().skip(3).take(10) {
+                let below: Vec<u32> = variant.ladder().take_while(|&t| t < tile).collect();
+                let made = below.iter().any(|&low| {
+                    variant.merge(&vec![low; variant.group()]) == Some(tile)
+                        || below
+                            .iter()
+                            .any(|&high| variant.merge(&[low, high]) == Some(tile))
+                });
+                assert!(made, "{} {tile}", variant.name());
+            }
+        }
+    }
+
+    #[test]
+    fn walls_split_lines_into_segments() {
+        let mut b = board("2,2,#,2/2,#,2,0/0,0,0,0/0,0,0,0", Rules::default());
+        assert!(b.slide(Direction::Left));
+        assert_eq!(b.to_text(), "4,0,#,2/2,#,2,0/0,0,0,0/0,0,0,0 4");
+        assert!(b.slide(Direction::Right));
+        assert_eq!(b.to_text(), "0,4,#,2/2,#,0,2/0,0,0,0/0,0,0,0 4");
+    }
+
+    #[test]
+    fn special_tiles_merge_by_their_own_rules() {
+        let mut b = board("*,8,0,0/o,2,0,0/4d,4,0,0/2,0,0,0", Rules::default());
+        assert!(b.slide(Direction::Left));
+        assert_eq!(b.to_text(), "16,0,0,0/o,2,0,0/16,0,0,0/2,0,0,0 32");
+
+        let mut b = board("4b,4,0,2/2,0,0,0/0,0,0,0/0,0,0,0", Rules::default());
+        assert!(b.slide(Direction::Left));
+        assert_eq!(b.to_text(), "0,0,0,0/2,0,0,0/0,0,0,0/0,0,0,0 8 10");
+        assert_eq!(b.calculate_score(), 12);
+    }
+
+    #[test]
+    fn gravity_cascades_score_their_multiplier() {
+        let rules = Rules {
+            gravity: true,
+            ..Rules::default()
+        };
+        let mut b = board("4,0,0,0/2,0,0,0/2,0,0,0/0,0,0,0", rules);
+        let steps = b.settle();
+        let passes: Vec<(u32, u32)> = steps.iter().map(|s| (s.multiplier, s.points)).collect();
+        assert_eq!(passes, [(2, 8), (3, 24)]);
+        assert_eq!(steps.last().map(|s| s.size), Some(b.size));
+        assert_eq!(b.to_text(), "0,0,0,0/0,0,0,0/0,0,0,0/8,0,0,0 32 20");
+        assert_eq!(b.calculate_score(), 28);
+
+        assert!(b.settle().is_empty());
+        b.rules.gravity = false;
+        b.size[0][0] = 2;
+        assert!(b.settle().is_empty());
+    }
+
+    #[test]
+    fn quarter_turns_undo_each_other() {
+        let mut b = board(
+            "2,#,4b,0/8,*,0,16/o,32d,0,2/4,0,#,64 12 3",
+            Rules::default(),
+        );
+        let original = b.clone();
+        let same =
+            |a: &Board, b: &Board| (a.size, a.walls, a.special) == (b.size, b.walls, b.special);
+
+        b.rotate_clockwise();
+        assert!(!same(&b, &original));
+        assert_eq!(b.size[0][3], 2);
+        b.rotate_counterclockwise();
+        assert!(same(&b, &original));
+
+        for _ in 0..4 {
+            b.rotate_counterclockwise();
+        }
+        assert!(same(&b, &original));
+
+        let mut small = board("2,4,8/0,#,16/32,0,64", Rules::default());
+        let original = small.clone();
+        small.rotate_counterclockwise();
+        assert_eq!(small.to_text(), "8,16,64/4,#,0/2,0,32 0");
+        small.rotate_clockwise();
+        assert!(same(&small, &original));
+    }
+
+    #[test]
+    fn spawns_are_named_after_the_variant_tiles() {
+        let describe = |variant| Spawns::ALL.map(|spawns| spawns.describe(variant));
+        assert_eq!(
+            describe(Variant::Classic),
+            ["random", "2s only", "4s only", "off"]
+        );
+        assert_eq!(
+            describe(Variant::Fibonacci),
+            ["random", "1s only", "2s only", "off"]
+        );
+        assert_eq!(
+            describe(Variant::PowersOfThree),
+            ["random", "3s only", "9s only", "off"]
+        );
+        for spawns in Spawns::ALL {
+            assert_eq!(spawns.describe(Variant::Classic), spawns.name());
+        }
+    }
+
+    #[test]
+    fn promote_steps_up_the_ladder() {
+        assert_eq!(Variant::Classic.promote(8), 16);
+        assert_eq!(Variant::Fibonacci.promote(5), 8);
+        assert_eq!(Variant::PowersOfThree.promote(9), 27);
+        assert_eq!(Variant::Threes.promote(2), 3);
+        assert_eq!(Variant::Threes.promote(3), 6);
+        assert_eq!(Variant::Classic.promote(1 << 31), 1 << 31);
+    }
+
+    #[test]
+    fn wrapping_lines_merge_over_the_seam() {
+        let rules = Rules {
+            wrap: true,
+            ..Rules::default()
+        };
+        let mut b = board("2,0,0,2/4,8,0,2/0,0,0,0/0,0,0,0", rules);
+        let line = |row| [(row, 0), (row, 1), (row, 2), (row, 3)];
+        assert_eq!(b.seam_shift(&line(0)), 1);
+        assert_eq!(b.seam_shift(&line(1)), 0);
+        assert_eq!(b.seam_shift(&line(2)), 0);
+        b.size[2] = [4, 0, 2, 0];
+        assert_eq!(b.seam_shift(&line(2)), 1);
+        b.size[2] = [0; 4];
+
+        assert!(b.slide(Direction::Left));
+        assert_eq!(b.to_text(), "0,0,0,4/4,8,2,0/0,0,0,0/0,0,0,0 4");
+    }
+
+    #[test]
+    fn wrapping_lines_turn_at_their_last_wall() {
+        let rules = Rules {
+            wrap: true,
+            ..Rules::default()
+        };
+        let b = board("2,#,0,2/0,0,0,0/0,0,0,0/0,0,0,0", rules);
+        assert_eq!(b.seam_shift(&[(0, 0), (0, 1), (0, 2), (0, 3)]), 2);
+    }
+
+    /// How often each cell and tile came up over `runs` spawns on copies of `start`.
+    fn spawn_counts(start: &Board, runs: u64) -> HashMap<((usize, usize), u32), u32> {
+        let mut counts = HashMap::new();
+        for seed in 0..runs {
+            let mut b = start.clone();
+            b.reseed(seed);
+            b.spawn_one_random();
+            let cell = b
+                .positions()
+                .find(|&(row, col)| b.size[row][col] != start.size[row][col]);
+            let cell = cell.unwrap();
+            *counts.entry((cell, b.size[cell.0][cell.1])).or_default() += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn easy_spawns_keep_away_from_the_biggest_tile() {
+        let easy = Rules {
+            difficulty: Difficulty::Easy,
+            ..Rules::default()
+        };
+        let counts = spawn_counts(&board("64,0,0,0/0,0,0,0/0,0,0,0/0,0,0,0 0", easy), 2000);
+        let at = |cell| {
+            counts
+                .iter()
+                .filter(|(k, _)| k.0 == cell)
+                .map(|(_, n)| n)
+                .sum::<u32>()
+        };
+        assert!(at((3, 3)) > 10 * at((0, 1)), "{counts:?}");
+    }
+
+    #[test]
+    fn hard_spawns_bigger_tiles() {
+        let empty = "0,0,0,0/0,0,0,0/0,0,0,0/0,0,0,0 0";
+        let tiles = |difficulty| {
+            let rules = Rules {
+                difficulty,
+                ..Rules::default()
+            };
+            let mut tiles = HashMap::<u32, u32>::new();
+            for ((_, tile), n) in spawn_counts(&board(empty, rules), 2000) {
+                *tiles.entry(tile).or_default() += n;
             }
+            tiles
+        };
+        let normal = tiles(Difficulty::Normal);
+        let hard = tiles(Difficulty::Hard);
+        assert_eq!(normal.get(&8), None);
+        assert!(hard[&8] > 0);
+        assert!(hard[&4] > 2 * normal[&4], "{hard:?} {normal:?}");
+    }
+
+    #[test]
+    fn evil_spawns_are_left_to_the_caller() {
+        let text = "2,4,0,0/0,8,0,0/0,0,0,0/0,0,0,16 0";
+        let evil = Rules {
+            difficulty: Difficulty::Evil,
+            ..Rules::default()
+        };
+        let mut a = board(text, evil);
+        let mut b = board(text, Rules::default());
+        a.spawn_one_random();
+        b.spawn_one_random();
+        assert_eq!(a.size, b.size);
+
+        let cell = a.empty_cells()[0];
+        a.spawn_with(|board| Some((board.empty_cells()[0], 4)));
+        assert_eq!(a.size[cell.0][cell.1], 4);
+        a.spawn_with(|_| None);
+        assert_eq!(a.empty_cells().len(), 16 - 6);
+    }
+
+    #[test]
+    fn pressure_time_shrinks_towards_the_goal() {
+        let rules = Rules {
+            pressure_seconds: 10,
+            ..Rules::default()
+        };
+        assert_eq!(rules.move_seconds(0), 10.0);
+        assert_eq!(rules.move_seconds(2), 10.0);
+        assert_eq!(rules.move_seconds(64), 7.5);
+        assert_eq!(rules.move_seconds(2048), 5.0);
+        assert_eq!(rules.move_seconds(8192), 5.0);
+
+        let fibonacci = Rules {
+            variant: Variant::Fibonacci,
+            ..rules
+        };
+        assert_eq!(fibonacci.move_seconds(fibonacci.goal()), 5.0);
+        assert!(fibonacci.move_seconds(89) < 10.0);
+
+        for pressure in Pressure::ALL {
+            assert_eq!(pressure.name().parse::<Pressure>(), Ok(pressure));
         }
     }
+
+    #[test]
+    fn text_round_trips() {
+        for text in [
+            "2,#,4b,0/8,*,0,16/o,32d,0,2/4,0,#,64 12 3",
+            "2,4,8/0,#,16/32,0,64 40",
+            "2,0/0,2 0",
+        ] {
+            let b = board(text, Rules::default());
+            assert_eq!(b.to_text(), text);
+            let mut copy = Board::seeded(1);
+            copy.load_text(&b.to_text()).unwrap();
+            assert_eq!(
+                (copy.size, copy.walls, copy.special, copy.dim),
+                (b.size, b.walls, b.special, b.dim)
+            );
+            assert_eq!((copy.merge_score, copy.bonus), (b.merge_score, b.bonus));
+        }
+
+        let mut b = Board::seeded(0);
+        assert!(b.load_text("2,2/2 0").is_err());
+        assert!(b.load_text("2/2 0").is_err());
+        assert!(b.load_text("2,x/2,2 0").is_err());
+        assert!(b.load_text("2,2/2,2 0 many").is_err());
+    }
+
+    #[test]
+    fn loaded_tiles_belong_to_the_variant() {
+        let mut b = Board::seeded(0);
+        assert!(b.load_text("3,0/0,6 0").is_err());
+        assert!(b.load_text("2,0/0,3b 0").is_err());
+        b.rules.variant = Variant::Threes;
+        b.load_text("3,0/0,6 0").unwrap();
+        assert!(b.load_text("4,0/0,0 0").is_err());
+        b.rules.variant = Variant::Fibonacci;
+        b.load_text("1,2/3,5d 0").unwrap();
+    }
 }
@@ -0,0 +1,341 @@
+//! `r2048 arena --engine <program> [-- <args...>]` lets an external program play on the real [`Board`] rules.
+//!
+//! The engine is spawned once and talks to the arena over stdin/stdout, one command per line.
+//! Lines sent by the arena:
+//!
+//! | line                            | meaning                                                   |
+//! |---------------------------------|-----------------------------------------------------------|
+//! | `r2048 1`                       | handshake, `1` is the protocol version                    |
+//! | `newgame <n>`                   | game `n` starts                                           |
+//! | `board <16 numbers>`            | the cells in row-major order, `0` is an empty cell        |
+//! | `legal <moves...>`              | the moves that change the board, e.g. `legal up left`     |
+//! | `go`                            | the engine must answer with a move                        |
+//! | `illegal <move>`                | the last reply was rejected, answer again                 |
+//! | `result <reason> <score> <max>` | game over, `reason` is `nomoves`, `illegal` or `timeout`  |
+//! | `quit`                          | the match is over, the engine should exit                 |
+//!
+//! Lines sent by the engine:
+//!
+//! | line             | meaning                                                 |
+//! |------------------|---------------------------------------------------------|
+//! | `ready [name]`   | answer to the handshake                                 |
+//! | `move <move>`    | answer to `go`, `<move>` is `up`, `down`, `left`, `right` |
+//!
+//! Any other line from the engine is ignored, so engines are free to print debug output. An
+//! engine that does not answer within the timeout forfeits the game and is restarted.
+//!
+//! Game `n` spawns its tiles from seed `S + n`, `S` being `--seed` (0 by default), so two
+//! engines given the same seed play the same games.
+
+use crate::board::{Board, Direction};
+use crate::cli::args::Args;
+use color_eyre::eyre::{WrapErr, bail};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Protocol version sent in the handshake.
+const PROTOCOL_VERSION: u32 = 1;
+
+/// How a game in the arena ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// No legal move was left.
+    NoMoves,
+    /// The engine kept sending illegal or malformed moves.
+    Illegal,
+    /// The engine did not answer in time or exited.
+    Timeout,
+}
+
+impl Outcome {
+    pub fn name(self) -> &'static str {
+        match self {
+            Outcome::NoMoves => "nomoves",
+            Outcome::Illegal => "illegal",
+            Outcome::Timeout => "timeout",
+        }
+    }
+}
+
+/// Summary of one arena game.
+#[derive(Debug, Clone)]
+pub struct GameReport {
+    pub outcome: Outcome,
+    pub score: u32,
+    pub max_tile: u32,
+    pub moves: u32,
+    pub illegal: u32,
+    pub think_time: Duration,
+}
+
+/// A running engine process.
+struct Engine {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+impl Engine {
+    fn spawn(program: &str, args: &[String]) -> color_eyre::Result<Self> {
+        if program.is_empty() {
+            bail!("empty engine command");
+        }
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .wrap_err_with(|| format!("failed to start engine `{program}`"))?;
+
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            child,
+            stdin,
+            lines,
+        })
+    }
+
+    fn send(&mut self, line: &str) {
+        // A dead engine is noticed when its next reply times out.
+        let _ = writeln!(self.stdin, "{line}").and_then(|_| self.stdin.flush());
+    }
+
+    /// Waits for the next line whose first word is `keyword` and returns the rest of it.
+    fn expect(&self, keyword: &str, timeout: Duration) -> Option<String> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match self.lines.recv_timeout(left) {
+                Ok(line) => {
+                    let mut words = line.trim().splitn(2, ' ');
+                    if words.next() == Some(keyword) {
+                        return Some(words.next().unwrap_or("").trim().to_string());
+                    }
+                }
+                Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => return None,
+            }
+        }
+    }
+
+    fn handshake(&mut self, timeout: Duration) -> color_eyre::Result<String> {
+        self.send(&format!("r2048 {PROTOCOL_VERSION}"));
+        match self.expect("ready", timeout) {
+            Some(name) if !name.is_empty() => Ok(name),
+            Some(_) => Ok("engine".to_string()),
+            None => bail!("engine did not answer the handshake with `ready`"),
+        }
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        self.send("quit");
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// `r2048 arena --engine <program> [--games N] [--timeout-ms MS] [--max-illegal N] [--seed S]
+/// [-- <args...>]`, the arguments after `--` going to the engine untouched.
+pub fn run(args: &[String]) -> color_eyre::Result<()> {
+    let args = Args::new(args);
+    let program = args.required("--engine")?;
+    let games: u32 = args.parse("--games", 1)?;
+    let timeout = Duration::from_millis(args.parse("--timeout-ms", 1000)?);
+    let max_illegal: u32 = args.parse("--max-illegal", 3)?;
+    let seed: u64 = args.parse("--seed", 0)?;
+
+    let mut engine = Engine::spawn(program, args.rest())?;
+    let name = engine.handshake(timeout * 5)?;
+
+    let mut reports = Vec::new();
+    for game in 1..=games {
+        let board = Board::seeded(seed.wrapping_add(u64::from(game)));
+        let report = play_game(&mut engine, board, game, timeout, max_illegal);
+        println!(
+            "game {game:>4}: {:<8} score {:>6}  max tile {:>5}  moves {:>5}  illegal {:>3}",
+            report.outcome.name(),
+            report.score,
+            report.max_tile,
+            report.moves,
+            report.illegal
+        );
+        if report.outcome == Outcome::Timeout && game < games {
+            engine = Engine::spawn(program, args.rest())?;
+            engine.handshake(timeout * 5)?;
+        }
+        reports.push(report);
+    }
+
+    print_summary(&name, &reports);
+    Ok(())
+}
+
+/// Plays game number `game` from the empty `board`.
+fn play_game(
+    engine: &mut Engine,
+    mut board: Board,
+    game: u32,
+    timeout: Duration,
+    max_illegal: u32,
+) -> GameReport {
+    board.init_board();
+    engine.send(&format!("newgame {game}"));
+
+    let mut report = GameReport {
+        outcome: Outcome::NoMoves,
+        score: 0,
+        max_tile: 0,
+        moves: 0,
+        illegal: 0,
+        think_time: Duration::ZERO,
+    };
+
+    'game: loop {
+        let legal = board.legal_moves();
+        if legal.is_empty() {
+            break;
+        }
+
+        let cells: Vec<String> = board.size.iter().flatten().map(u32::to_string).collect();
+        let names: Vec<&str> = legal.iter().map(|d| d.name()).collect();
+        engine.send(&format!("board {}", cells.join(" ")));
+        engine.send(&format!("legal {}", names.join(" ")));
+        engine.send("go");
+
+        loop {
+            let start = Instant::now();
+            let reply = engine.expect("move", timeout);
+            report.think_time += start.elapsed();
+
+            let Some(reply) = reply else {
+                report.outcome = Outcome::Timeout;
+                break 'game;
+            };
+            match reply.parse::<Direction>() {
+                Ok(direction) if legal.contains(&direction) => {
+                    board.slide(direction);
+                    board.spawn_one_random();
+                    report.moves += 1;
+                    break;
+                }
+                _ => {
+                    report.illegal += 1;
+                    if report.illegal > max_illegal {
+                        report.outcome = Outcome::Illegal;
+                        break 'game;
+                    }
+                    engine.send(&format!("illegal {reply}"));
+                }
+            }
+        }
+    }
+
+    report.score = board.calculate_score();
+    report.max_tile = board.max_tile();
+    engine.send(&format!(
+        "result {} {} {}",
+        report.outcome.name(),
+        report.score,
+        report.max_tile
+    ));
+    report
+}
+
+fn print_summary(name: &str, reports: &[GameReport]) {
+    if reports.is_empty() {
+        return;
+    }
+    let games = reports.len() as f64;
+    let total: u64 = reports.iter().map(|r| u64::from(r.score)).sum();
+    let best = reports.iter().map(|r| r.score).max().unwrap_or(0);
+    let wins = reports.iter().filter(|r| r.max_tile >= 2048).count();
+    let forfeits = reports
+        .iter()
+        .filter(|r| r.outcome != Outcome::NoMoves)
+        .count();
+    let moves: u32 = reports.iter().map(|r| r.moves).sum();
+    let think: Duration = reports.iter().map(|r| r.think_time).sum();
+
+    println!(
+        "{name}: {} games, average {:.1}, best {best}, reached 2048 in {wins}, forfeited {forfeits}, {:.2?} per move",
+        reports.len(),
+        total as f64 / games,
+        think / moves.max(1)
+    );
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    /// An engine written in shell that answers `go` and `illegal` with `reply`, `$first` being the first
+    /// legal move.
+    fn shell_engine(reply: &str) -> Engine {
+        let script = format!(
+            "while read cmd rest; do case $cmd in \
+             r2048) echo 'debug line'; echo 'ready shbot';; \
+             legal) set -- $rest; first=$1;; \
+             go | illegal) {reply};; \
+             quit) exit 0;; \
+             esac; done"
+        );
+        Engine::spawn("sh", &["-c".to_string(), script]).unwrap()
+    }
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn plays_legal_moves_to_the_end() {
+        let mut engine = shell_engine("echo \"move $first\"");
+        assert_eq!(engine.handshake(TIMEOUT).unwrap(), "shbot");
+        let first = play_game(&mut engine, Board::seeded(7), 1, TIMEOUT, 3);
+        assert_eq!(first.outcome, Outcome::NoMoves);
+        assert_eq!(first.illegal, 0);
+        assert!(first.moves > 10);
+
+        // The same seed deals the same tiles, so the same engine plays the same game.
+        let again = play_game(&mut engine, Board::seeded(7), 2, TIMEOUT, 3);
+        assert_eq!(
+            (again.score, again.max_tile, again.moves),
+            (first.score, first.max_tile, first.moves)
+        );
+    }
+
+    #[test]
+    fn forfeits_bad_engines() {
+        let mut engine = shell_engine("echo 'move sideways'");
+        engine.handshake(TIMEOUT).unwrap();
+        let report = play_game(&mut engine, Board::seeded(0), 1, TIMEOUT, 2);
+        assert_eq!(
+            (report.outcome, report.illegal, report.moves),
+            (Outcome::Illegal, 3, 0)
+        );
+
+        let mut engine = shell_engine(":");
+        engine.handshake(TIMEOUT).unwrap();
+        let report = play_game(
+            &mut engine,
+            Board::seeded(0),
+            1,
+            Duration::from_millis(50),
+            2,
+        );
+        assert_eq!(report.outcome, Outcome::Timeout);
+    }
+}
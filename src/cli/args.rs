@@ -0,0 +1,77 @@
+use color_eyre::eyre::{WrapErr, eyre};
+use std::str::FromStr;
+
+/// Minimal `--flag value` parser shared by the subcommands. Everything after a bare `--` is
+/// left alone, see [`Args::rest`].
+pub struct Args {
+    args: Vec<String>,
+    rest: Vec<String>,
+}
+
+impl Args {
+    pub fn new(args: &[String]) -> Self {
+        let (args, rest) = match args.iter().position(|a| a == "--") {
+            Some(i) => (&args[..i], &args[i + 1..]),
+            None => (args, &[][..]),
+        };
+        Self {
+            args: args.to_vec(),
+            rest: rest.to_vec(),
+        }
+    }
+
+    /// The arguments after `--`, passed on verbatim.
+    pub fn rest(&self) -> &[String] {
+        &self.rest
+    }
+
+    /// Returns the value following `flag`, if present.
+    pub fn value(&self, flag: &str) -> Option<&str> {
+        self.args
+            .iter()
+            .position(|a| a == flag)
+            .and_then(|i| self.args.get(i + 1))
+            .map(String::as_str)
+    }
+
+    /// Parses the value following `flag`, falling back to `default` when it is missing.
+    pub fn parse<T: FromStr>(&self, flag: &str, default: T) -> color_eyre::Result<T>
+    where
+        T::Err: std::error::Error + Send + Sync + 'static,
+    {
+        match self.value(flag) {
+            Some(v) => v
+                .parse()
+                .wrap_err_with(|| format!("invalid value for {flag}: {v}")),
+            None => Ok(default),
+        }
+    }
+
+    /// Parses a comma-separated list following `flag`, `None` when the flag is missing.
+    pub fn list<T: FromStr>(&self, flag: &str) -> color_eyre::Result<Option<Vec<T>>>
+    where
+        T::Err: std::error::Error + Send + Sync + 'static,
+    {
+        let Some(v) = self.value(flag) else {
+            return Ok(None);
+        };
+        v.split(',')
+            .map(|item| {
+                item.trim()
+                    .parse()
+                    .wrap_err_with(|| format!("invalid value for {flag}: {item}"))
+            })
+            .collect::<color_eyre::Result<_>>()
+            .map(Some)
+    }
+
+    /// Like [`Args::value`] but fails when the flag is missing.
+    pub fn required(&self, flag: &str) -> color_eyre::Result<&str> {
+        self.value(flag)
+            .ok_or_else(|| eyre!("missing required {flag}"))
+    }
+
+    pub fn has(&self, flag: &str) -> bool {
+        self.args.iter().any(|a| a == flag)
+    }
+}
@@ -0,0 +1,334 @@
+use crate::ai::strategy::{self, Expectimax};
+use crate::board::{Board, Direction};
+use crate::cli::args::Args;
+use color_eyre::eyre::bail;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// Columns of every record, in the order they are written.
+pub const COLUMNS: [&str; 27] = [
+    "game",
+    "move",
+    "c0",
+    "c1",
+    "c2",
+    "c3",
+    "c4",
+    "c5",
+    "c6",
+    "c7",
+    "c8",
+    "c9",
+    "c10",
+    "c11",
+    "c12",
+    "c13",
+    "c14",
+    "c15",
+    "action",
+    "eval_up",
+    "eval_down",
+    "eval_left",
+    "eval_right",
+    "final_score",
+    "final_merge_score",
+    "final_max_tile",
+    "game_moves",
+];
+
+/// One position of a finished game, seen through one of its symmetries.
+struct Record {
+    game: u32,
+    index: u32,
+    board: Board,
+    action: Direction,
+    evaluations: [Option<f64>; 4],
+}
+
+/// How a game ended, shared by all of its records.
+struct Outcome {
+    score: u32,
+    merge_score: u32,
+    max_tile: u32,
+    moves: u32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Csv,
+    Jsonl,
+    Npy,
+}
+
+/// `r2048 export-dataset --out PATH [--format csv|jsonl|npy] [--games N]
+/// [--strategy NAME] [--symmetries 1|2|4|8] [--seed S]`
+///
+/// Plays `N` games and writes one record per move: the board, the move played, the evaluation
+/// of each move, and how the game ended. Strategies that don't rate moves are scored with
+/// expectimax. `--symmetries` writes every position under that many of its rotations and
+/// reflections, with the move and evaluations remapped to match. `npy` files hold one float32
+/// matrix with the columns of [`COLUMNS`], moves as their index in up, down, left, right and
+/// illegal moves evaluated as NaN.
+pub fn run(args: &[String]) -> color_eyre::Result<()> {
+    let args = Args::new(args);
+    let out = args.required("--out")?;
+    let format = match args.value("--format").unwrap_or("csv") {
+        "csv" => Format::Csv,
+        "jsonl" => Format::Jsonl,
+        "npy" => Format::Npy,
+        other => bail!("unknown format `{other}`, expected csv, jsonl or npy"),
+    };
+    let games: u32 = args.parse("--games", 10)?;
+    let seed: u64 = args.parse("--seed", 0)?;
+    let symmetries: usize = args.parse("--symmetries", 1)?;
+    if ![1, 2, 4, 8].contains(&symmetries) {
+        bail!("--symmetries must be 1, 2, 4 or 8");
+    }
+    let name = args.value("--strategy").unwrap_or("expectimax");
+    let mut strategy = strategy::by_name(name)?;
+    let evaluator = Expectimax::new(2);
+
+    let mut out = BufWriter::new(File::create(out)?);
+    let mut rows: Vec<[f32; COLUMNS.len()]> = Vec::new();
+    if format == Format::Csv {
+        writeln!(out, "{}", COLUMNS.join(","))?;
+    }
+
+    let mut written = 0;
+    for game in 0..games {
+        let mut board = Board::seeded(seed.wrapping_add(u64::from(game)));
+        board.init_board();
+
+        let mut records = Vec::new();
+        while !board.lost() {
+            let evaluations = strategy
+                .evaluations(&board)
+                .unwrap_or_else(|| evaluator.evaluate_moves(&board));
+            let action = strategy.choose(&board);
+            let before = board.clone();
+            if !board.slide(action) {
+                break;
+            }
+            board.spawn_one_random();
+
+            for k in 0..symmetries {
+                let mut remapped = [None; 4];
+                for d in Direction::ALL {
+                    remapped[d.symmetry(k).index()] = evaluations[d.index()];
+                }
+                records.push(Record {
+                    game,
+                    index: records.len() as u32 / symmetries as u32,
+                    board: before.symmetry(k),
+                    action: action.symmetry(k),
+                    evaluations: remapped,
+                });
+            }
+        }
+
+        let outcome = Outcome {
+            score: board.calculate_score(),
+            merge_score: board.merge_score,
+            max_tile: board.max_tile(),
+            moves: records.len() as u32 / symmetries as u32,
+        };
+        for record in &records {
+            match format {
+                Format::Csv => write_csv(&mut out, record, &outcome)?,
+                Format::Jsonl => write_json(&mut out, record, &outcome)?,
+                Format::Npy => rows.push(row(record, &outcome)),
+            }
+        }
+        written += records.len();
+        eprintln!(
+            "game {:>4}: score {:>6}  max tile {:>5}  moves {:>5}",
+            game + 1,
+            outcome.score,
+            outcome.max_tile,
+            outcome.moves
+        );
+    }
+
+    if format == Format::Npy {
+        write_npy(&mut out, &rows)?;
+    }
+    out.flush()?;
+    eprintln!("{written} records written");
+    Ok(())
+}
+
+fn cells(board: &Board) -> impl Iterator<Item = u32> + '_ {
+    board.size.iter().flatten().copied()
+}
+
+fn write_csv(out: &mut impl Write, record: &Record, outcome: &Outcome) -> std::io::Result<()> {
+    let mut fields = vec![record.game.to_string(), record.index.to_string()];
+    fields.extend(cells(&record.board).map(|v| v.to_string()));
+    fields.push(record.action.name().to_string());
+    fields.extend(
+        record
+            .evaluations
+            .iter()
+            .map(|e| e.map_or(String::new(), |v| format!("{v:.3}"))),
+    );
+    fields.extend([
+        outcome.score.to_string(),
+        outcome.merge_score.to_string(),
+        outcome.max_tile.to_string(),
+        outcome.moves.to_string(),
+    ]);
+    writeln!(out, "{}", fields.join(","))
+}
+
+fn write_json(out: &mut impl Write, record: &Record, outcome: &Outcome) -> std::io::Result<()> {
+    let board: Vec<String> = cells(&record.board).map(|v| v.to_string()).collect();
+    let evaluations: Vec<String> = record
+        .evaluations
+        .iter()
+        .map(|e| e.map_or("null".to_string(), |v| format!("{v:.3}")))
+        .collect();
+    writeln!(
+        out,
+        r#"{{"game":{},"move":{},"board":[{}],"action":"{}","evals":[{}],"final_score":{},"final_merge_score":{},"final_max_tile":{},"game_moves":{}}}"#,
+        record.game,
+        record.index,
+        board.join(","),
+        record.action.name(),
+        evaluations.join(","),
+        outcome.score,
+        outcome.merge_score,
+        outcome.max_tile,
+        outcome.moves
+    )
+}
+
+fn row(record: &Record, outcome: &Outcome) -> [f32; COLUMNS.len()] {
+    let mut row = [0.0; COLUMNS.len()];
+    let values = [record.game as f32, record.index as f32]
+        .into_iter()
+        .chain(cells(&record.board).map(|v| v as f32))
+        .chain([record.action.index() as f32])
+        .chain(
+            record
+                .evaluations
+                .iter()
+                .map(|e| e.map_or(f32::NAN, |v| v as f32)),
+        )
+        .chain([
+            outcome.score as f32,
+            outcome.merge_score as f32,
+            outcome.max_tile as f32,
+            outcome.moves as f32,
+        ]);
+    for (cell, value) in row.iter_mut().zip(values) {
+        *cell = value;
+    }
+    row
+}
+
+/// Writes `rows` as a version 1.0 `.npy` file of little-endian float32.
+fn write_npy(out: &mut impl Write, rows: &[[f32; COLUMNS.len()]]) -> std::io::Result<()> {
+    let mut header = format!(
+        "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}",
+        rows.len(),
+        COLUMNS.len()
+    );
+    // magic (6) + version (2) + header length (2) + header + newline must be a multiple of 64
+    while (10 + header.len() + 1) % 64 != 0 {
+        header.push(' ');
+    }
+    header.push('\n');
+
+    out.write_all(b"\x93NUMPY\x01\x00")?;
+    out.write_all(&(header.len() as u16).to_le_bytes())?;
+    out.write_all(header.as_bytes())?;
+    for value in rows.iter().flatten() {
+        out.write_all(&value.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn symmetries_commute_with_moves_and_ratings() {
+        let mut board = Board::seeded(0);
+        board
+            .load_text("2,4,0,8/0,16,2,0/4,0,0,2/0,32,2,2 0")
+            .unwrap();
+        let evaluator = Expectimax::new(2);
+        let values = evaluator.evaluate_moves(&board);
+        for k in 0..8 {
+            let seen = board.symmetry(k);
+            let seen_values = evaluator.evaluate_moves(&seen);
+            for d in Direction::ALL {
+                let moved = board.after(d).map(|b| b.symmetry(k).to_text());
+                let seen_moved = seen.after(d.symmetry(k)).map(|b| b.to_text());
+                assert_eq!(moved, seen_moved, "symmetry {k}, {}", d.name());
+                let (a, b) = (values[d.index()], seen_values[d.symmetry(k).index()]);
+                assert_eq!(a.is_some(), b.is_some());
+                if let (Some(a), Some(b)) = (a, b) {
+                    assert!((a - b).abs() < 1e-6 * a.abs().max(1.0), "{a} {b}");
+                }
+            }
+        }
+    }
+
+    fn tiles(record: &[&str]) -> Vec<u32> {
+        record[2..18].iter().map(|v| v.parse().unwrap()).collect()
+    }
+
+    fn position(record: &[&str]) -> Board {
+        let rows: Vec<String> = tiles(record)
+            .chunks(4)
+            .map(|row| row.iter().map(u32::to_string).collect::<Vec<_>>().join(","))
+            .collect();
+        let mut board = Board::seeded(0);
+        board.load_text(&rows.join("/")).unwrap();
+        board
+    }
+
+    #[test]
+    fn every_symmetry_gets_a_record() {
+        let path = std::env::temp_dir().join(format!("r2048-export-{}.csv", std::process::id()));
+        let args: Vec<String> = [
+            "--out",
+            path.to_str().unwrap(),
+            "--games",
+            "1",
+            "--strategy",
+            "corner",
+            "--symmetries",
+            "8",
+            "--seed",
+            "3",
+        ]
+        .map(String::from)
+        .to_vec();
+        run(&args).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let mut lines = text.lines();
+        assert_eq!(lines.next().unwrap(), COLUMNS.join(","));
+        let records: Vec<Vec<&str>> = lines.map(|l| l.split(',').collect()).collect();
+        let moves: usize = records[0][26].parse().unwrap();
+        assert_eq!(records.len(), moves * 8);
+        for group in records.chunks(8) {
+            // One position seen eight ways: the same tiles, and a move that is legal on each.
+            let mut first = tiles(&group[0]);
+            first.sort_unstable();
+            for record in group {
+                assert_eq!(record[1], group[0][1]);
+                let mut same = tiles(record);
+                same.sort_unstable();
+                assert_eq!(same, first);
+                let action: Direction = record[18].parse().unwrap();
+                assert!(position(record).after(action).is_some());
+            }
+        }
+    }
+}
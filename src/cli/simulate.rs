@@ -0,0 +1,110 @@
+use crate::ai::adversary;
+use crate::ai::ntuple::{NTuple, NTupleNetwork};
+use crate::ai::search::{self, Deep};
+use crate::ai::solver::{Perfect, Table};
+use crate::ai::strategy::{self, Strategy};
+use crate::board::{Board, Difficulty, MAX_DIM, Rules, Variant};
+use crate::cli::args::Args;
+use color_eyre::eyre::{bail, eyre};
+use std::path::Path;
+use std::time::Instant;
+
+/// `r2048 simulate [--strategy NAME] [--games N] [--size DIM] [--target TILE] [--variant NAME]
+/// [--difficulty NAME] [--evil-depth D] [--weights PATH] [--table PATH] [--depth D] [--threads T]
+/// [--exact]`
+///
+/// Plays `N` games with a built-in strategy and prints a summary, counting the games that reach
+/// the target tile (2048 by default, rounded up to a tile of `--variant`). `--weights` picks the
+/// file the `ntuple` strategy loads and `--table` the one the `perfect` strategy loads. `--depth`
+/// and `--threads` tune the `deep` strategy, and `--exact` turns its pruning off. `--difficulty
+/// evil` stress-tests the strategy against the adversarial spawner, looking `--evil-depth`
+/// rounds ahead.
+pub fn run(args: &[String]) -> color_eyre::Result<()> {
+    let args = Args::new(args);
+    let name = args.value("--strategy").unwrap_or("expectimax");
+    let games: u32 = args.parse("--games", 10)?;
+    let dim: usize = args.parse("--size", MAX_DIM)?;
+    let rules = Rules {
+        target: args.parse("--target", Rules::default().target)?,
+        variant: match args.value("--variant") {
+            Some(variant) => variant.parse().map_err(|e: String| eyre!(e))?,
+            None => Variant::Classic,
+        },
+        difficulty: match args.value("--difficulty") {
+            Some(difficulty) => difficulty.parse().map_err(|e: String| eyre!(e))?,
+            None => Difficulty::Normal,
+        },
+        evil_depth: args.parse("--evil-depth", Rules::default().evil_depth)?,
+        ..Rules::default()
+    };
+    if !(1..=2).contains(&rules.evil_depth) {
+        bail!("--evil-depth must be 1 or 2, not {}", rules.evil_depth);
+    }
+    let mut strategy: Box<dyn Strategy> = match (args.value("--weights"), args.value("--table")) {
+        (Some(path), _) if name == "ntuple" => {
+            Box::new(NTuple::new(NTupleNetwork::load(Path::new(path))?))
+        }
+        (_, Some(path)) if name == "perfect" => {
+            Box::new(Perfect::with_table(Table::load(Path::new(path))?))
+        }
+        _ if name == "deep" => {
+            let mut deep = Deep::new(
+                args.parse("--depth", search::DEFAULT_DEPTH)?,
+                args.parse("--threads", search::default_threads())?,
+            );
+            deep.prune = !args.has("--exact");
+            Box::new(deep)
+        }
+        _ => strategy::by_name(name)?,
+    };
+
+    let start = Instant::now();
+    let (mut total, mut wins, mut best) = (0u64, 0u32, 0u32);
+    for game in 1..=games {
+        let mut board = Board::sized(dim, rand::random());
+        board.rules = rules;
+        let (board, moves) = play_game(strategy.as_mut(), board);
+        let score = board.calculate_score();
+        total += u64::from(score);
+        best = best.max(score);
+        if board.won() {
+            wins += 1;
+        }
+        println!(
+            "game {game:>4}: score {score:>6}  max tile {:>5}  moves {moves:>5}",
+            board.max_tile()
+        );
+    }
+
+    if games > 0 {
+        println!(
+            "{} on {}: {games} games, average {:.1}, best {best}, reached {} in {wins} ({:.1}%), {:.2?}",
+            strategy.name(),
+            rules.difficulty.name(),
+            total as f64 / f64::from(games),
+            rules.goal(),
+            f64::from(wins) * 100.0 / f64::from(games),
+            start.elapsed()
+        );
+    }
+    if let Some(report) = strategy.report() {
+        println!("{report}");
+    }
+    Ok(())
+}
+
+/// Plays a full game from a fresh board until no move is left or the strategy picks a move that
+/// does not change the board. Returns the final board and the number of moves played.
+pub fn play_game(strategy: &mut dyn Strategy, mut board: Board) -> (Board, u32) {
+    board.init_board();
+    let mut moves = 0;
+    while !board.lost() {
+        let direction = strategy.choose(&board);
+        if !board.slide(direction) {
+            break;
+        }
+        adversary::spawn(&mut board);
+        moves += 1;
+    }
+    (board, moves)
+}
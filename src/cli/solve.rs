@@ -0,0 +1,64 @@
+use crate::ai::solver::{self, Objective, Table};
+use crate::cli::args::Args;
+use color_eyre::eyre::eyre;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Instant;
+
+/// Stack size for the solver thread, the search recurses once per move of the longest game.
+const SOLVER_STACK: usize = 256 * 1024 * 1024;
+
+/// `r2048 solve [--size 2|3] [--target TILE] [--out PATH]`
+///
+/// Solves a small board exactly and saves the table. Without `--target` the table holds the
+/// expected merge score under perfect play, with it the probability of reaching that tile.
+/// Boards are 2×2 unless `--size 3` is given, a 3×3 solve is only practical with a `--target`.
+pub fn run(args: &[String]) -> color_eyre::Result<()> {
+    let args = Args::new(args);
+    let dim: usize = args.parse("--size", 2)?;
+    let objective = match args.value("--target") {
+        Some(_) => Objective::Reach(args.parse("--target", 0)?),
+        None => Objective::Score,
+    };
+    let out = args
+        .value("--out")
+        .map_or_else(|| solver::default_path(dim), PathBuf::from);
+
+    if dim == 3 && objective == Objective::Score {
+        println!(
+            "a 3x3 solve without --target explores every position, it takes hours and gigabytes"
+        );
+    }
+    let start = Instant::now();
+    let table = thread::Builder::new()
+        .stack_size(SOLVER_STACK)
+        .spawn(move || {
+            Table::solve_with_progress(dim, objective, |solved| {
+                println!("{solved:>11} positions solved, {:.0?}", start.elapsed());
+            })
+        })?
+        .join()
+        .map_err(|_| eyre!("solver thread panicked"))??;
+
+    if let Some(parent) = out.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    table.save(&out)?;
+
+    let what = match objective {
+        Objective::Score => "expected merge score".to_string(),
+        Objective::Reach(target) => format!("chance of reaching {target}"),
+    };
+    println!(
+        "{dim}x{dim}: {} positions solved in {:.2?}, {what} from a new game: {:.4}",
+        table.len(),
+        start.elapsed(),
+        table.start_value()
+    );
+    println!(
+        "table saved to {} ({} bytes)",
+        out.display(),
+        std::fs::metadata(&out)?.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use crate::ai::ntuple::{self, NTupleNetwork};
+use crate::cli::args::Args;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// `r2048 train [--episodes N] [--alpha A] [--seed S] [--load PATH] [--out PATH]
+/// [--report N] [--curve PATH]`
+///
+/// Trains an n-tuple network by self-play and saves its weights. Every `--report` episodes the
+/// averages over that window are printed, and appended to `--curve` as CSV when given.
+pub fn run(args: &[String]) -> color_eyre::Result<()> {
+    let args = Args::new(args);
+    let episodes: u64 = args.parse("--episodes", 10_000)?;
+    let alpha: f32 = args.parse("--alpha", 0.1)?;
+    let seed: u64 = args.parse("--seed", 0)?;
+    let report: u64 = args.parse::<u64>("--report", 1000)?.max(1);
+    let out = args
+        .value("--out")
+        .map_or_else(ntuple::default_path, PathBuf::from);
+
+    let mut network = match args.value("--load") {
+        Some(path) => NTupleNetwork::load(path.as_ref())?,
+        None => NTupleNetwork::default(),
+    };
+
+    let mut curve = match args.value("--curve") {
+        Some(path) => {
+            let mut file = File::create(path)?;
+            writeln!(
+                file,
+                "episodes,score,merge_score,max_tile,rate_1024,rate_2048"
+            )?;
+            Some(file)
+        }
+        None => None,
+    };
+
+    if let Some(parent) = out.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let start = Instant::now();
+    let (mut score, mut merge, mut max, mut r1024, mut r2048) = (0u64, 0u64, 0u32, 0u64, 0u64);
+    for episode in 1..=episodes {
+        let board = network.learn_episode(seed.wrapping_add(episode), alpha);
+        score += u64::from(board.calculate_score());
+        merge += u64::from(board.merge_score);
+        max = max.max(board.max_tile());
+        r1024 += u64::from(board.max_tile() >= 1024);
+        r2048 += u64::from(board.max_tile() >= 2048);
+
+        if episode % report == 0 || episode == episodes {
+            let window = (episode - 1) % report + 1;
+            let n = window as f64;
+            println!(
+                "episode {episode:>8}: score {:>8.1}  merge score {:>8.1}  max tile {max:>5}  1024 {:>5.1}%  2048 {:>5.1}%  {:.0?}",
+                score as f64 / n,
+                merge as f64 / n,
+                r1024 as f64 * 100.0 / n,
+                r2048 as f64 * 100.0 / n,
+                start.elapsed()
+            );
+            if let Some(file) = curve.as_mut() {
+                writeln!(
+                    file,
+                    "{episode},{:.1},{:.1},{max},{:.4},{:.4}",
+                    score as f64 / n,
+                    merge as f64 / n,
+                    r1024 as f64 / n,
+                    r2048 as f64 / n
+                )?;
+            }
+            network.save(&out)?;
+            (score, merge, max, r1024, r2048) = (0, 0, 0, 0, 0);
+        }
+    }
+
+    println!("weights saved to {}", out.display());
+    Ok(())
+}
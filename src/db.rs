@@ -1,58 +1,383 @@
+use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use rusqlite::{Connection, Result, params};
 
+use crate::board::{Difficulty, MAX_DIM, Rules};
+
+/// Directory holding the score database and other files the game keeps between sessions:
+/// `R2048_DATA_DIR` when it is set, otherwise `r2048` in the user's data directory, such as
+/// `~/.local/share/r2048` on Linux.
+pub fn data_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("R2048_DATA_DIR") {
+        return PathBuf::from(dir);
+    }
+    let home = || env::var_os("HOME").map(PathBuf::from);
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home().map(|home| home.join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home().map(|home| home.join(".local/share")))
+    };
+    base.unwrap_or_default().join("r2048")
+}
+
+/// Where earlier versions kept the score database, inside the source tree they were built from.
+fn legacy_database() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/db/r2048.db")
+}
+
+/// Name progress is saved under, the login name of whoever is playing.
+pub fn profile() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "player".to_string())
+}
+
+/// A position saved from the editor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SavedPosition {
+    /// The cells, as written by [`crate::board::Board::to_text`].
+    pub board: String,
+    /// Does a tile spawn before the first move?
+    pub spawn_first: bool,
+    /// Name of the spawn behaviour, see [`crate::board::Spawns::name`].
+    pub spawns: String,
+}
+
 #[derive(Debug)]
 pub struct Score {
     conn: Connection,
+    /// Profile the puzzle progress belongs to.
+    pub profile: String,
 }
 
 impl Score {
     pub fn new() -> Result<Self> {
-        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-        path.push("src/db/r2048.db");
+        let mut path = data_dir();
+        path.push("r2048.db");
 
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent).expect("Failed to create dir");
         }
+        // Brings the scores of earlier versions along the first time.
+        let legacy = legacy_database();
+        if !path.exists() && legacy.exists() {
+            let _ = fs::copy(legacy, &path);
+        }
 
+        Self::open(&path)
+    }
+
+    /// Opens the database at `path`, creating the tables it lacks.
+    pub fn open(path: &Path) -> Result<Self> {
         let conn = Connection::open(path)?;
         conn.execute(
-            "CREATE TABLE IF NOT EXISTS data (
-                id INTEGER PRIMARY KEY CHECK (id = 1),
+            "CREATE TABLE IF NOT EXISTS best_scores (
+                rules TEXT NOT NULL,
+                difficulty TEXT NOT NULL,
+                score INTEGER NOT NULL,
+                PRIMARY KEY (rules, difficulty)
+            )",
+            [],
+        )?;
+        // Earlier versions kept a single high score, from classic games on normal.
+        let legacy: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'data'",
+            [],
+            |row| row.get(0),
+        )?;
+        if legacy {
+            conn.execute(
+                "INSERT OR IGNORE INTO best_scores (rules, difficulty, score)
+                 SELECT ?1, 'normal', score FROM data WHERE id = 1",
+                params![Rules::default().signature(MAX_DIM)],
+            )?;
+        }
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS time_attack (
+                id INTEGER PRIMARY KEY,
+                seconds INTEGER NOT NULL,
+                rules TEXT NOT NULL,
+                difficulty TEXT NOT NULL,
                 score INTEGER NOT NULL
             )",
             [],
         )?;
 
-        Ok(Self { conn })
-    }
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS puzzles (
+                profile TEXT NOT NULL,
+                puzzle TEXT NOT NULL,
+                best_moves INTEGER,
+                PRIMARY KEY (profile, puzzle)
+            )",
+            [],
+        )?;
 
-    #[inline(always)]
-    pub fn update_score(&self, new_score: u32) -> Result<()> {
-        let current_high: u32 = self
-            .conn
-            .query_row("SELECT score FROM data WHERE id = 1", [], |row| row.get(0))
-            .unwrap_or(0);
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS positions (
+                profile TEXT NOT NULL,
+                name TEXT NOT NULL,
+                board TEXT NOT NULL,
+                spawn_first INTEGER NOT NULL,
+                spawns TEXT NOT NULL,
+                PRIMARY KEY (profile, name)
+            )",
+            [],
+        )?;
 
-        if new_score > current_high {
-            self.conn.execute(
-                "INSERT INTO data (id, score) VALUES (1, ?1)
-                 ON CONFLICT(id) DO UPDATE SET score = excluded.score",
-                params![new_score],
-            )?;
-        }
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS powered_runs (
+                id INTEGER PRIMARY KEY,
+                board TEXT NOT NULL,
+                rules TEXT NOT NULL,
+                difficulty TEXT NOT NULL,
+                score INTEGER NOT NULL,
+                powerups INTEGER NOT NULL
+            )",
+            [],
+        )?;
 
+        Ok(Self {
+            conn,
+            profile: profile(),
+        })
+    }
+
+    /// Keeps `new_score` as the high score of `rules` on `difficulty` if it beats it. `rules`
+    /// is a [`Rules::signature`], as for every score below.
+    pub fn update_score(&self, rules: &str, difficulty: Difficulty, new_score: u32) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO best_scores (rules, difficulty, score) VALUES (?1, ?2, ?3)
+             ON CONFLICT(rules, difficulty) DO UPDATE SET score = MAX(score, excluded.score)",
+            params![rules, difficulty.name(), new_score],
+        )?;
         Ok(())
     }
 
-    #[inline(always)]
-    pub fn get_score(&self) -> Result<u32> {
+    /// High score of classic games by `rules` on `difficulty`, 0 before the first one.
+    pub fn get_score(&self, rules: &str, difficulty: Difficulty) -> Result<u32> {
         let score = self
             .conn
-            .query_row("SELECT score FROM data WHERE id = 1", [], |row| row.get(0))
+            .query_row(
+                "SELECT score FROM best_scores WHERE rules = ?1 AND difficulty = ?2",
+                params![rules, difficulty.name()],
+                |row| row.get(0),
+            )
             .unwrap_or(0);
         Ok(score)
     }
+
+    /// Adds the result of a time-attack game played with a clock of `seconds`.
+    pub fn record_time_attack(
+        &self,
+        seconds: u32,
+        rules: &str,
+        difficulty: Difficulty,
+        score: u32,
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO time_attack (seconds, rules, difficulty, score) VALUES (?1, ?2, ?3, ?4)",
+            params![seconds, rules, difficulty.name(), score],
+        )?;
+        Ok(())
+    }
+
+    /// The best `limit` time-attack scores for a clock of `seconds` by `rules` on `difficulty`,
+    /// highest first.
+    pub fn time_attack_scores(
+        &self,
+        seconds: u32,
+        rules: &str,
+        difficulty: Difficulty,
+        limit: u32,
+    ) -> Result<Vec<u32>> {
+        let mut statement = self.conn.prepare(
+            "SELECT score FROM time_attack WHERE seconds = ?1 AND rules = ?2 AND difficulty = ?3
+             ORDER BY score DESC LIMIT ?4",
+        )?;
+        statement
+            .query_map(params![seconds, rules, difficulty.name(), limit], |row| {
+                row.get(0)
+            })?
+            .collect()
+    }
+
+    /// Adds a game where `powerups` power-ups were used. These are kept apart from the other
+    /// scores, on the leaderboard named by [`crate::board::Mode::leaderboard`].
+    pub fn record_powered(
+        &self,
+        board: &str,
+        rules: &str,
+        difficulty: Difficulty,
+        score: u32,
+        powerups: u32,
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO powered_runs (board, rules, difficulty, score, powerups)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![board, rules, difficulty.name(), score, powerups],
+        )?;
+        Ok(())
+    }
+
+    /// The best `limit` scores of games played with power-ups on `board` by `rules` and on
+    /// `difficulty`, highest first.
+    pub fn powered_scores(
+        &self,
+        board: &str,
+        rules: &str,
+        difficulty: Difficulty,
+        limit: u32,
+    ) -> Result<Vec<u32>> {
+        let mut statement = self.conn.prepare(
+            "SELECT score FROM powered_runs WHERE board = ?1 AND rules = ?2 AND difficulty = ?3
+             ORDER BY score DESC LIMIT ?4",
+        )?;
+        statement
+            .query_map(params![board, rules, difficulty.name(), limit], |row| {
+                row.get(0)
+            })?
+            .collect()
+    }
+
+    /// Saves an attempt at `puzzle`, with the number of moves it took when solved. The best
+    /// solution is kept.
+    pub fn record_puzzle(&self, puzzle: &str, solved_in: Option<u32>) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO puzzles (profile, puzzle, best_moves) VALUES (?1, ?2, ?3)
+             ON CONFLICT(profile, puzzle) DO UPDATE SET best_moves = CASE
+                 WHEN best_moves IS NULL THEN excluded.best_moves
+                 WHEN excluded.best_moves IS NULL THEN best_moves
+                 ELSE MIN(best_moves, excluded.best_moves)
+             END",
+            params![self.profile, puzzle, solved_in],
+        )?;
+        Ok(())
+    }
+
+    /// Fewest moves `puzzle` was solved in, `None` if it is still unsolved.
+    pub fn puzzle_best(&self, puzzle: &str) -> Result<Option<u32>> {
+        let best = self
+            .conn
+            .query_row(
+                "SELECT best_moves FROM puzzles WHERE profile = ?1 AND puzzle = ?2",
+                params![self.profile, puzzle],
+                |row| row.get(0),
+            )
+            .unwrap_or(None);
+        Ok(best)
+    }
+
+    /// Saves `position` under `name`, replacing any position with that name.
+    pub fn save_position(&self, name: &str, position: &SavedPosition) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO positions (profile, name, board, spawn_first, spawns)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                self.profile,
+                name,
+                position.board,
+                position.spawn_first,
+                position.spawns
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Names of the saved positions, in alphabetical order.
+    pub fn position_names(&self) -> Result<Vec<String>> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT name FROM positions WHERE profile = ?1 ORDER BY name")?;
+        statement
+            .query_map(params![self.profile], |row| row.get(0))?
+            .collect()
+    }
+
+    pub fn load_position(&self, name: &str) -> Result<SavedPosition> {
+        self.conn.query_row(
+            "SELECT board, spawn_first, spawns FROM positions WHERE profile = ?1 AND name = ?2",
+            params![self.profile, name],
+            |row| {
+                Ok(SavedPosition {
+                    board: row.get(0)?,
+                    spawn_first: row.get(1)?,
+                    spawns: row.get(2)?,
+                })
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A database in a fresh file, removed when `test` is done with it.
+    fn with_db(name: &str, test: impl FnOnce(&Path)) {
+        let path = std::env::temp_dir().join(format!("r2048-db-{}-{name}.db", std::process::id()));
+        let _ = fs::remove_file(&path);
+        test(&path);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn the_old_high_score_becomes_a_classic_one() {
+        with_db("legacy", |path| {
+            let conn = Connection::open(path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE data (id INTEGER PRIMARY KEY CHECK (id = 1), score INTEGER NOT NULL);
+                 INSERT INTO data (id, score) VALUES (1, 1234);",
+            )
+            .unwrap();
+            drop(conn);
+
+            let classic = Rules::default().signature(MAX_DIM);
+            let db = Score::open(path).unwrap();
+            assert_eq!(db.get_score(&classic, Difficulty::Normal).unwrap(), 1234);
+            assert_eq!(db.get_score(&classic, Difficulty::Hard).unwrap(), 0);
+            db.update_score(&classic, Difficulty::Normal, 2000).unwrap();
+            drop(db);
+
+            // Opening it again doesn't bring the old score back.
+            let db = Score::open(path).unwrap();
+            assert_eq!(db.get_score(&classic, Difficulty::Normal).unwrap(), 2000);
+        });
+    }
+
+    #[test]
+    fn time_attack_scores_rank_by_clock_and_rules() {
+        with_db("time-attack", |path| {
+            let db = Score::open(path).unwrap();
+            let classic = Rules::default().signature(MAX_DIM);
+            let small = Rules::default().signature(3);
+            for (seconds, rules, difficulty, score) in [
+                (60, &classic, Difficulty::Normal, 300),
+                (60, &classic, Difficulty::Normal, 900),
+                (60, &classic, Difficulty::Normal, 500),
+                (60, &classic, Difficulty::Hard, 700),
+                (120, &classic, Difficulty::Normal, 2000),
+                (60, &small, Difficulty::Normal, 100),
+            ] {
+                db.record_time_attack(seconds, rules, difficulty, score)
+                    .unwrap();
+            }
+            let scores = |seconds, rules, difficulty, limit| {
+                db.time_attack_scores(seconds, rules, difficulty, limit)
+                    .unwrap()
+            };
+            assert_eq!(scores(60, &classic, Difficulty::Normal, 5), [900, 500, 300]);
+            assert_eq!(scores(60, &classic, Difficulty::Normal, 2), [900, 500]);
+            assert_eq!(scores(60, &classic, Difficulty::Hard, 5), [700]);
+            assert_eq!(scores(120, &classic, Difficulty::Normal, 5), [2000]);
+            assert_eq!(scores(60, &small, Difficulty::Normal, 5), [100]);
+            assert_eq!(scores(30, &classic, Difficulty::Normal, 5), []);
+        });
+    }
 }
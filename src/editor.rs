@@ -0,0 +1,212 @@
+//! Position editor: set up any board by hand, then play it out or ask a bot about it.
+
+use crate::board::{Board, Direction, Spawns, Variant};
+
+/// Who plays first from the edited position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToMove {
+    /// The player slides first.
+    Player,
+    /// A tile spawns before the first move.
+    Spawn,
+}
+
+impl ToMove {
+    pub fn name(self) -> &'static str {
+        match self {
+            ToMove::Player => "player",
+            ToMove::Spawn => "spawn",
+        }
+    }
+}
+
+/// State of [`crate::board::State::Editor`].
+#[derive(Debug, Clone)]
+pub struct Editor {
+    /// The position being edited, its rules are used when it is played.
+    pub board: Board,
+    pub cursor: (usize, usize),
+    /// Digits typed for the cell under the cursor, written to it by [`Editor::commit`].
+    pub input: String,
+    pub to_move: ToMove,
+    /// How the edited board rates each move, with the name of the bot that rated it.
+    pub evaluations: Option<(String, [Option<f64>; 4])>,
+    /// Name being typed for [`crate::db::Score::save_position`], while saving.
+    pub naming: Option<String>,
+    /// Names in the position library.
+    pub library: Vec<String>,
+    /// Index of the highlighted entry in [`Editor::library`].
+    pub selected: usize,
+    /// Last error or confirmation, shown under the board.
+    pub message: Option<String>,
+}
+
+impl Editor {
+    /// An empty `dim`×`dim` board of `variant`.
+    pub fn new(dim: usize, variant: Variant) -> Self {
+        let mut board = Board::sized(dim, 0);
+        board.rules.variant = variant;
+        Self {
+            board,
+            cursor: (0, 0),
+            input: String::new(),
+            to_move: ToMove::Player,
+            evaluations: None,
+            naming: None,
+            library: Vec::new(),
+            selected: 0,
+            message: None,
+        }
+    }
+
+    /// Commits the typed value, then moves the cursor one cell, staying on the board.
+    pub fn move_cursor(&mut self, direction: Direction) {
+        self.commit();
+        let last = self.board.dim - 1;
+        let (row, col) = self.cursor;
+        self.cursor = match direction {
+            Direction::Up => (row.saturating_sub(1), col),
+            Direction::Down => ((row + 1).min(last), col),
+            Direction::Left => (row, col.saturating_sub(1)),
+            Direction::Right => (row, (col + 1).min(last)),
+        };
+    }
+
+    /// Writes the typed value into the cell under the cursor. Values must be on the ladder of
+    /// the board's variant, `0` clears the cell.
+    pub fn commit(&mut self) {
+        if self.input.is_empty() {
+            return;
+        }
+        let input = std::mem::take(&mut self.input);
+        let variant = self.board.rules.variant;
+        match input.parse::<u32>() {
+            Ok(value) if value == 0 || variant.is_tile(value) => self.set(value),
+            _ => self.message = Some(format!("{input} is not a {} tile", variant.name())),
+        }
+    }
+
+    /// Puts `value` under the cursor, `0` for an empty cell. Replaces a wall.
+    pub fn set(&mut self, value: u32) {
+        let (row, col) = self.cursor;
+        self.board.size[row][col] = value;
+        self.board.walls[row][col] = false;
+        self.board.special[row][col] = None;
+        self.evaluations = None;
+        self.message = None;
+    }
+
+    /// Value under the cursor.
+    pub fn current(&self) -> u32 {
+        let (row, col) = self.cursor;
+        self.board.size[row][col]
+    }
+
+    /// Moves the tile under the cursor one step up the variant's ladder, an empty cell gets the
+    /// smallest tile.
+    pub fn double(&mut self) {
+        let value = self.current();
+        self.set(self.board.rules.variant.promote(value));
+    }
+
+    /// Moves the tile under the cursor one step down the variant's ladder, the smallest tile
+    /// becomes an empty cell.
+    pub fn halve(&mut self) {
+        let value = self.current();
+        self.set(self.board.rules.variant.demote(value));
+    }
+
+    /// Builds a wall under the cursor, or knocks it down.
+    pub fn toggle_wall(&mut self) {
+        let (row, col) = self.cursor;
+        let wall = !self.board.walls[row][col];
+        self.set(0);
+        self.board.walls[row][col] = wall;
+    }
+
+    pub fn clear(&mut self) {
+        self.board.clear();
+        self.board.walls = [[false; 4]; 4];
+        self.board.merge_score = 0;
+        self.board.bonus = 0;
+        self.evaluations = None;
+        self.message = None;
+    }
+
+    pub fn toggle_to_move(&mut self) {
+        self.to_move = match self.to_move {
+            ToMove::Player => ToMove::Spawn,
+            ToMove::Spawn => ToMove::Player,
+        };
+    }
+
+    /// Switches to the next entry of [`Spawns::ALL`].
+    pub fn cycle_spawns(&mut self) {
+        let current = Spawns::ALL
+            .iter()
+            .position(|&s| s == self.board.rules.spawns);
+        let next = current.map_or(0, |i| (i + 1) % Spawns::ALL.len());
+        self.board.rules.spawns = Spawns::ALL[next];
+        self.evaluations = None;
+    }
+
+    /// The position ready to be played: reseeded with `seed`, and with its first spawn placed
+    /// when the spawn is [`ToMove`].
+    pub fn start(&self, seed: u64) -> Board {
+        let mut board = self.board.clone();
+        board.reseed(seed);
+        if self.to_move == ToMove::Spawn {
+            board.spawn_one_random();
+        }
+        board
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(editor: &mut Editor, input: &str) -> u32 {
+        editor.input = input.to_string();
+        editor.commit();
+        editor.current()
+    }
+
+    #[test]
+    fn typed_tiles_follow_the_variant() {
+        let mut editor = Editor::new(4, Variant::Classic);
+        assert_eq!(typed(&mut editor, "16"), 16);
+        assert_eq!(typed(&mut editor, "3"), 16);
+        assert_eq!(editor.message.as_deref(), Some("3 is not a classic tile"));
+        assert_eq!(typed(&mut editor, "0"), 0);
+
+        let mut editor = Editor::new(4, Variant::PowersOfThree);
+        assert_eq!(typed(&mut editor, "27"), 27);
+        assert_eq!(typed(&mut editor, "8"), 27);
+    }
+
+    #[test]
+    fn stepping_walks_the_ladder() {
+        let mut editor = Editor::new(3, Variant::Fibonacci);
+        let mut up = Vec::new();
+        for _ in 0..5 {
+            editor.double();
+            up.push(editor.current());
+        }
+        assert_eq!(up, [1, 2, 3, 5, 8]);
+        let mut down = Vec::new();
+        for _ in 0..5 {
+            editor.halve();
+            down.push(editor.current());
+        }
+        assert_eq!(down, [5, 3, 2, 1, 0]);
+
+        let mut editor = Editor::new(3, Variant::Threes);
+        editor.set(3);
+        editor.double();
+        assert_eq!(editor.current(), 6);
+        editor.halve();
+        editor.halve();
+        assert_eq!(editor.current(), 2);
+    }
+}
@@ -0,0 +1,231 @@
+//! A reinforcement-learning environment around [`Board`], in the style of OpenAI Gym.
+//!
+//! ```
+//! use r2048::board::Direction;
+//! use r2048::env::{Encoding, Env};
+//!
+//! let mut env = Env::new(Encoding::Log2);
+//! let mut observation = env.reset(42);
+//! loop {
+//!     let action = Direction::ALL[env.action_mask().iter().position(|&m| m).unwrap()];
+//!     let step = env.step(action);
+//!     observation = step.observation;
+//!     if step.done {
+//!         break;
+//!     }
+//! }
+//! # let _ = observation;
+//! ```
+
+use crate::board::{Board, Direction};
+
+/// How a [`Board`] is turned into an [`Observation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// Tile values as they are, shape `[4, 4]`.
+    Raw,
+    /// `log2` of each tile, `0` for empty cells, shape `[4, 4]`. Tiles of the other variants
+    /// get their [`crate::board::Variant::code`], their place on the ladder plus one.
+    Log2,
+    /// One plane per exponent, shape `[planes, 4, 4]`. Plane `0` marks empty cells and tiles
+    /// too large for the last plane are put in it.
+    OneHot { planes: usize },
+}
+
+/// A flattened tensor in row-major order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Observation {
+    pub data: Vec<f32>,
+    pub shape: Vec<usize>,
+}
+
+/// Extra information returned by [`Env::step`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Info {
+    /// Did the action change the board? Illegal actions are no-ops that don't spawn a tile.
+    pub legal: bool,
+    /// Sum of the tiles on the board.
+    pub score: u32,
+    /// Sum of every merge so far, see [`Board::merge_score`].
+    pub merge_score: u32,
+    pub max_tile: u32,
+    pub moves: u32,
+}
+
+/// Result of [`Env::step`].
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub observation: Observation,
+    /// Value of the tiles created by merges during this step.
+    pub reward: f32,
+    /// True once no legal action is left.
+    pub done: bool,
+    pub info: Info,
+}
+
+/// A single-player 2048 environment. Games are fully determined by the seed given to
+/// [`Env::reset`] and the actions taken.
+#[derive(Debug, Clone)]
+pub struct Env {
+    pub encoding: Encoding,
+    board: Board,
+    moves: u32,
+}
+
+impl Env {
+    pub fn new(encoding: Encoding) -> Self {
+        Self {
+            encoding,
+            board: Board::seeded(0),
+            moves: 0,
+        }
+    }
+
+    /// Starts a new game whose spawns are drawn from `seed`.
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.board = Board::seeded(seed);
+        self.board.init_board();
+        self.moves = 0;
+        self.observe()
+    }
+
+    pub fn step(&mut self, action: Direction) -> Step {
+        let before = self.board.merge_score;
+        let legal = self.board.slide(action);
+        if legal {
+            self.board.spawn_one_random();
+            self.moves += 1;
+        }
+
+        Step {
+            observation: self.observe(),
+            reward: (self.board.merge_score - before) as f32,
+            done: self.board.lost(),
+            info: self.info(legal),
+        }
+    }
+
+    /// Which entries of [`Direction::ALL`] would change the board.
+    pub fn action_mask(&self) -> [bool; 4] {
+        Direction::ALL.map(|d| self.board.after(d).is_some())
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn observe(&self) -> Observation {
+        encode(&self.board, self.encoding)
+    }
+
+    fn info(&self, legal: bool) -> Info {
+        Info {
+            legal,
+            score: self.board.calculate_score(),
+            merge_score: self.board.merge_score,
+            max_tile: self.board.max_tile(),
+            moves: self.moves,
+        }
+    }
+}
+
+/// Encodes `board` with `encoding`.
+pub fn encode(board: &Board, encoding: Encoding) -> Observation {
+    let exponent = |v: u32| board.rules.variant.code(v) as usize;
+    let cells = board.size.iter().flatten();
+
+    match encoding {
+        Encoding::Raw => Observation {
+            data: cells.map(|&v| v as f32).collect(),
+            shape: vec![4, 4],
+        },
+        Encoding::Log2 => Observation {
+            data: cells.map(|&v| exponent(v) as f32).collect(),
+            shape: vec![4, 4],
+        },
+        Encoding::OneHot { planes } => {
+            let planes = planes.max(1);
+            let mut data = vec![0.0; planes * 16];
+            for (i, &v) in cells.enumerate() {
+                data[exponent(v).min(planes - 1) * 16 + i] = 1.0;
+            }
+            Observation {
+                data,
+                shape: vec![planes, 4, 4],
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Plays `env` from `seed`, always taking the first legal action, and returns every step.
+    fn play(env: &mut Env, seed: u64) -> Vec<(Vec<f32>, f32, bool)> {
+        env.reset(seed);
+        let mut steps = Vec::new();
+        loop {
+            let Some(first) = env.action_mask().iter().position(|&m| m) else {
+                return steps;
+            };
+            let step = env.step(Direction::ALL[first]);
+            steps.push((step.observation.data, step.reward, step.done));
+            if step.done {
+                return steps;
+            }
+        }
+    }
+
+    #[test]
+    fn a_seed_replays_the_same_game() {
+        let mut env = Env::new(Encoding::Raw);
+        let first = play(&mut env, 7);
+        assert!(first.last().unwrap().2);
+        assert_eq!(play(&mut env, 7), first);
+        assert_eq!(play(&mut Env::new(Encoding::Raw), 7), first);
+        assert_ne!(play(&mut env, 8), first);
+    }
+
+    #[test]
+    fn illegal_actions_change_nothing() {
+        let mut env = Env::new(Encoding::Raw);
+        env.reset(3);
+        env.board.size = [[2, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0]];
+        assert_eq!(env.action_mask(), [false, true, false, true]);
+        let start = env.observe();
+        let step = env.step(Direction::Up);
+        assert!(!step.info.legal);
+        assert_eq!((step.reward, step.done, step.info.moves), (0.0, false, 0));
+        assert_eq!(step.observation, start);
+    }
+
+    #[test]
+    fn rewards_are_the_merged_tiles() {
+        let mut env = Env::new(Encoding::Raw);
+        env.reset(0);
+        env.board.size = [[2, 2, 4, 4], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0]];
+        let step = env.step(Direction::Left);
+        assert_eq!(step.reward, 12.0);
+        assert_eq!(step.info.merge_score, 12);
+        assert_eq!(step.info.moves, 1);
+    }
+
+    #[test]
+    fn encodings() {
+        let mut board = Board::seeded(0);
+        board.size = [[0, 2, 4, 2048], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0]];
+        let log2 = encode(&board, Encoding::Log2);
+        assert_eq!(log2.shape, [4, 4]);
+        assert_eq!(log2.data[..4], [0.0, 1.0, 2.0, 11.0]);
+
+        let one_hot = encode(&board, Encoding::OneHot { planes: 4 });
+        assert_eq!(one_hot.shape, [4, 4, 4]);
+        let plane = |cell: usize| (0..4).find(|&p| one_hot.data[p * 16 + cell] == 1.0);
+        assert_eq!(
+            [plane(0), plane(1), plane(2), plane(3)],
+            [0, 1, 2, 3].map(Some)
+        );
+        assert_eq!(one_hot.data.iter().sum::<f32>(), 16.0);
+    }
+}
@@ -1,14 +1,37 @@
+use crate::ai::adversary;
+use crate::ai::analysis::{self, MoveReview};
+use crate::ai::coach::{self, Warning};
+use crate::ai::meter::{Estimate, Meter};
+use crate::ai::strategy::{self, STRATEGIES};
+use crate::ai::thinker::Thinker;
 use crate::board::*;
-use crate::events::event::{AppEvent, Event, EventHandler};
+use crate::db::{self, SavedPosition};
+use crate::editor::{Editor, ToMove};
+use crate::events::event::{AppEvent, Event, EventHandler, TICK_FPS};
+use crate::powerup::{self, Inventory, PowerUp, Targeting};
+use crate::puzzle::{self, Puzzle};
 use crossterm::event::KeyEventKind;
+use rand::seq::IndexedRandom;
+use std::thread::{self, JoinHandle};
 
 use ratatui::{
     DefaultTerminal,
     crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
 };
 
-/// Application.
+/// Number of ticks between two autoplay moves.
+const AUTOPLAY_TICKS: u32 = 6;
+
+/// Number of ticks a toast stays on screen.
+const TOAST_TICKS: u32 = 60;
+
+/// Number of ticks each pass of a gravity cascade stays on screen.
+const CASCADE_TICKS: u32 = 6;
 
+/// Number of ticks the board takes to turn in a rotating game.
+pub const SPIN_TICKS: u32 = 9;
+
+/// Application.
 pub struct App {
     /// Is the application running?
     pub running: bool,
@@ -19,6 +42,103 @@ pub struct App {
 
     pub board: Board,
 
+    /// Side length of classic and time-attack games.
+    pub dim: usize,
+
+    /// Rules of classic and time-attack games, puzzles bring their own.
+    pub rules: Rules,
+
+    /// Kind of game being played.
+    pub mode: Mode,
+
+    /// Clock picked in the menu for the next time-attack game, in seconds.
+    pub time_limit: u32,
+
+    /// Ticks left before a time-attack game ends.
+    pub clock: u32,
+
+    /// Ticks left to move before a pressure game moves on, see [`Rules::pressure`].
+    pub pressure: u32,
+
+    /// Every puzzle that can be picked from [`State::Puzzles`].
+    pub puzzles: Vec<Puzzle>,
+
+    /// Why the packs left out of [`App::puzzles`] failed to load, see [`crate::puzzle::load_all`].
+    pub skipped_packs: Vec<String>,
+
+    /// Index of the highlighted entry in [`App::puzzles`].
+    pub selected_puzzle: usize,
+
+    /// Position being set up in [`State::Editor`].
+    pub editor: Editor,
+
+    /// High score storage.
+    pub db: db::Score,
+
+    /// Bot used for hints and autoplay.
+    pub strategy: Thinker,
+
+    /// Index of [`App::strategy`] in [`STRATEGIES`].
+    pub strategy_index: usize,
+
+    /// Suggested move, shown until the board changes.
+    pub hint: Option<Direction>,
+
+    /// Is the bot playing on its own?
+    pub autoplay: bool,
+
+    /// Ticks counted since the last autoplay move.
+    pub ticks: u32,
+
+    /// Moves of this game played by autoplay. A game the bot had a hand in isn't recorded.
+    pub bot_moves: u32,
+
+    /// Every position of the current game with the move played from it.
+    pub history: Vec<(Board, Direction)>,
+
+    /// Review of the last finished game, once [`App::analysis_job`] is done.
+    pub analysis: Option<Vec<MoveReview>>,
+
+    /// Background thread running the review.
+    pub analysis_job: Option<JoinHandle<Vec<MoveReview>>>,
+
+    /// Index of the highlighted entry in [`App::blunders`].
+    pub selected_review: usize,
+
+    /// Win-probability meter, when turned on.
+    pub meter: Option<Meter>,
+
+    /// Latest estimate from [`App::meter`].
+    pub estimate: Option<Estimate>,
+
+    /// Is the coach watching the moves?
+    pub coaching: bool,
+
+    /// Warnings given by the coach this game, with the move that earned them.
+    pub warnings: Vec<(usize, Warning)>,
+
+    /// Power-ups earned this game and not used yet.
+    pub inventory: Inventory,
+
+    /// Tiles being picked for a power-up, sliding is paused meanwhile.
+    pub targeting: Option<Targeting>,
+
+    /// Power-ups used this game, with the number of moves played before each.
+    pub powerups: Vec<(usize, PowerUp)>,
+
+    /// Tiles shown as progress towards the target, in increasing order.
+    pub milestones: Vec<u32>,
+
+    /// Passes of the last move's gravity cascade being shown, with the ticks since the move.
+    pub cascade: Option<(Vec<Cascade>, u32)>,
+
+    /// The board before its last turn, whether it turned clockwise and the ticks since, while
+    /// the turn is shown.
+    pub spin: Option<(Board, bool, u32)>,
+
+    /// Short message shown on the playing screen and the ticks it has left.
+    pub toast: Option<(String, u32)>,
+
     /// Event handler.
     pub events: EventHandler,
 }
@@ -34,6 +154,71 @@ impl Default for App {
 
             board: Board::new(),
 
+            dim: MAX_DIM,
+
+            rules: Rules::default(),
+
+            mode: Mode::Classic,
+
+            time_limit: TIME_LIMITS[0],
+
+            clock: 0,
+
+            pressure: 0,
+
+            puzzles: puzzle::builtin(),
+
+            skipped_packs: Vec::new(),
+            selected_puzzle: 0,
+
+            editor: Editor::new(MAX_DIM, Rules::default().variant),
+
+            db: db::Score::new().unwrap(),
+
+            strategy: Thinker::new(
+                strategy::by_name(STRATEGIES[0]).expect("the first bot plays without files"),
+            ),
+
+            strategy_index: 0,
+
+            hint: None,
+
+            autoplay: false,
+
+            ticks: 0,
+
+            bot_moves: 0,
+
+            history: Vec::new(),
+
+            analysis: None,
+
+            analysis_job: None,
+
+            selected_review: 0,
+
+            meter: None,
+
+            estimate: None,
+
+            coaching: false,
+
+            warnings: Vec::new(),
+
+            inventory: Inventory::default(),
+
+            targeting: None,
+
+            powerups: Vec::new(),
+
+            milestones: Rules::default().milestones(),
+
+            cascade: None,
+
+            spin: None,
+
+            toast: None,
+
             events: EventHandler::new(),
         }
     }
@@ -41,13 +226,11 @@ impl Default for App {
 
 impl App {
     /// Constructs a new instance of [`App`].
-
     pub fn new() -> Self {
         Self::default()
     }
 
     /// Run the application's main loop.
-
     pub fn run(mut self, mut terminal: DefaultTerminal) -> color_eyre::Result<()> {
         while self.running {
             self.handle_events()?;
@@ -60,13 +243,13 @@ impl App {
 
     pub fn handle_events(&mut self) -> color_eyre::Result<()> {
         match self.events.next()? {
-            Event::Tick => self.tick(),
-
-            Event::Crossterm(event) => match event {
-                crossterm::event::Event::Key(key_event) => self.handle_key_event(key_event)?,
+            Event::Tick => self.tick()?,
 
-                _ => {}
-            },
+            Event::Crossterm(event) => {
+                if let crossterm::event::Event::Key(key_event) = event {
+                    self.handle_key_event(key_event)?
+                }
+            }
 
             Event::App(app_event) => match app_event {
                 AppEvent::Quit => self.quit(),
@@ -77,7 +260,6 @@ impl App {
     }
 
     /// Handles the key events and updates the state of [`App`].
-
     pub fn handle_key_event(&mut self, key_event: KeyEvent) -> color_eyre::Result<()> {
         if key_event.kind != KeyEventKind::Press {
             return Ok(());
@@ -91,26 +273,77 @@ impl App {
                     self.events.send(AppEvent::Quit)
                 }
 
-                KeyCode::Left | KeyCode::Char('h') => {
-                    if self.selected_button > 0 {
-                        self.selected_button -= 1;
-                    }
+                KeyCode::Left | KeyCode::Char('h') if self.selected_button > 0 => {
+                    self.selected_button -= 1;
                 }
 
-                KeyCode::Right | KeyCode::Char('l') => {
-                    if self.selected_button < 1 {
-                        self.selected_button += 1;
-                    }
+                KeyCode::Right | KeyCode::Char('l') if self.selected_button < 4 => {
+                    self.selected_button += 1;
+                }
+
+                KeyCode::Char('t') => self.cycle_target(),
+
+                KeyCode::Char('v') => {
+                    let current = Variant::ALL.iter().position(|&v| v == self.rules.variant);
+                    let next = current.map_or(0, |i| (i + 1) % Variant::ALL.len());
+                    self.rules.variant = Variant::ALL[next];
+                    self.milestones = self.rules.milestones();
+                }
+
+                KeyCode::Char('r') => self.rules.wrap = !self.rules.wrap,
+
+                KeyCode::Char('f') => self.rules.gravity = !self.rules.gravity,
+
+                KeyCode::Char('o') => {
+                    let current = Rotation::ALL.iter().position(|&r| r == self.rules.rotation);
+                    let next = current.map_or(0, |i| (i + 1) % Rotation::ALL.len());
+                    self.rules.rotation = Rotation::ALL[next];
+                }
+
+                KeyCode::Char('p') => {
+                    let current = Pressure::ALL.iter().position(|&p| p == self.rules.pressure);
+                    let next = current.map_or(0, |i| (i + 1) % Pressure::ALL.len());
+                    self.rules.pressure = Pressure::ALL[next];
+                }
+
+                KeyCode::Char('s') => {
+                    self.rules.specials = if self.rules.specials.any() {
+                        Specials::default()
+                    } else {
+                        Specials::SOME
+                    };
+                }
+
+                KeyCode::Char('g') => {
+                    let current = Difficulty::ALL
+                        .iter()
+                        .position(|&d| d == self.rules.difficulty);
+                    let next = current.map_or(0, |i| (i + 1) % Difficulty::ALL.len());
+                    self.rules.difficulty = Difficulty::ALL[next];
+                }
+
+                KeyCode::Char('w') => {
+                    let current = Walls::ALL.iter().position(|&w| w == self.rules.walls);
+                    let next = current.map_or(0, |i| (i + 1) % Walls::ALL.len());
+                    self.rules.walls = Walls::ALL[next];
+                }
+
+                KeyCode::Char('d') => {
+                    let current = TIME_LIMITS.iter().position(|&t| t == self.time_limit);
+                    let next = current.map_or(0, |i| (i + 1) % TIME_LIMITS.len());
+                    self.time_limit = TIME_LIMITS[next];
                 }
 
                 KeyCode::Char('e') | KeyCode::Char('E') => match self.selected_button {
-                    0 => {
-                        self.state = State::Playing;
+                    0 => self.start_game(),
 
-                        self.board.init_board();
-                    }
+                    1 => self.start_time_attack(),
+
+                    2 => self.state = State::Puzzles,
 
-                    1 => self.events.send(AppEvent::Quit),
+                    3 => self.open_editor()?,
+
+                    4 => self.events.send(AppEvent::Quit),
 
                     _ => {}
                 },
@@ -118,66 +351,58 @@ impl App {
                 _ => {}
             },
 
-            State::Playing => {
-                let moved = match key_event.code {
-                    KeyCode::Down => {
-                        self.board.move_all_down();
-                        true
-                    }
-                    KeyCode::Up => {
-                        self.board.move_all_up();
-                        true
-                    }
-                    KeyCode::Right => {
-                        self.board.move_all_right();
-                        true
-                    }
-                    KeyCode::Left => {
-                        self.board.move_all_left();
-                        true
-                    }
-                    KeyCode::Esc | KeyCode::Char('q') => {
-                        self.state = State::Menu;
-                        false
-                    }
-                    _ => false,
-                };
-
-                if moved {
-                    self.board.spawn_one_random();
+            State::Playing if self.targeting.is_some() => self.handle_targeting_key(key_event)?,
 
-                    if self.board.won() {
-                        self.state = State::Won;
-                    } else if self.board.lost() {
-                        self.state = State::Lost;
-                    }
+            State::Playing => match key_event.code {
+                KeyCode::Down => self.play(Direction::Down)?,
+                KeyCode::Up => self.play(Direction::Up)?,
+                KeyCode::Right => self.play(Direction::Right)?,
+                KeyCode::Left => self.play(Direction::Left)?,
+                KeyCode::Char('h') => self.strategy.ask(&self.board),
+                KeyCode::Char('a') => self.autoplay = !self.autoplay,
+                KeyCode::Char(key @ '1'..='3') => {
+                    let index = key as usize - '1' as usize;
+                    self.use_powerup(PowerUp::ALL[index])?;
                 }
-            }
-            State::Won => match key_event.code {
+                KeyCode::Char('m') => self.toggle_meter(),
+                KeyCode::Char('c') if key_event.modifiers == KeyModifiers::CONTROL => {
+                    self.events.send(AppEvent::Quit)
+                }
+                KeyCode::Char('c') => {
+                    self.coaching = !self.coaching;
+                    let text = if self.coaching {
+                        "coach on"
+                    } else {
+                        "coach off"
+                    };
+                    self.show_toast(text);
+                }
+                KeyCode::Char('s') => self.next_strategy(),
+                KeyCode::Esc | KeyCode::Char('q') => {
+                    self.autoplay = false;
+                    self.leave_game();
+                }
+                _ => {}
+            },
+            State::Won | State::Lost => match key_event.code {
                 KeyCode::Char('c') if key_event.modifiers == KeyModifiers::CONTROL => {
                     self.events.send(AppEvent::Quit)
                 }
 
-                KeyCode::Left | KeyCode::Char('h') => {
-                    if self.selected_button > 0 {
-                        self.selected_button -= 1;
-                    }
+                KeyCode::Left | KeyCode::Char('h') if self.selected_button > 0 => {
+                    self.selected_button -= 1;
                 }
 
-                KeyCode::Right | KeyCode::Char('l') => {
-                    if self.selected_button < 1 {
-                        self.selected_button += 1;
-                    }
+                KeyCode::Right | KeyCode::Char('l') if self.selected_button < 2 => {
+                    self.selected_button += 1;
                 }
 
                 KeyCode::Char('e') | KeyCode::Char('E') => match self.selected_button {
-                    0 => {
-                        self.state = State::Playing;
+                    0 => self.restart(),
 
-                        self.board.init_board();
-                    }
+                    1 => self.start_analysis(),
 
-                    1 => self.state = State::Menu,
+                    2 => self.leave_game(),
 
                     _ => {}
                 },
@@ -185,34 +410,54 @@ impl App {
                 _ => {}
             },
 
-            State::Lost => match key_event.code {
+            State::Puzzles => match key_event.code {
                 KeyCode::Char('c') if key_event.modifiers == KeyModifiers::CONTROL => {
                     self.events.send(AppEvent::Quit)
                 }
 
-                KeyCode::Left | KeyCode::Char('h') => {
-                    if self.selected_button > 0 {
-                        self.selected_button -= 1;
-                    }
+                KeyCode::Up | KeyCode::Char('k') if self.selected_puzzle > 0 => {
+                    self.selected_puzzle -= 1;
                 }
 
-                KeyCode::Right | KeyCode::Char('l') => {
-                    if self.selected_button < 1 {
-                        self.selected_button += 1;
-                    }
+                KeyCode::Down | KeyCode::Char('j')
+                    if self.selected_puzzle + 1 < self.puzzles.len() =>
+                {
+                    self.selected_puzzle += 1;
                 }
 
-                KeyCode::Char('e') | KeyCode::Char('E') => match self.selected_button {
-                    0 => {
-                        self.state = State::Playing;
+                KeyCode::Char('e') | KeyCode::Char('E') | KeyCode::Enter
+                    if self.selected_puzzle < self.puzzles.len() =>
+                {
+                    self.start_puzzle(self.selected_puzzle);
+                }
 
-                        self.board.init_board();
-                    }
+                KeyCode::Esc | KeyCode::Char('q') => self.state = State::Menu,
 
-                    1 => self.state = State::Menu,
+                _ => {}
+            },
 
-                    _ => {}
-                },
+            State::Editor => self.handle_editor_key(key_event)?,
+
+            State::Analysis => match key_event.code {
+                KeyCode::Char('c') if key_event.modifiers == KeyModifiers::CONTROL => {
+                    self.events.send(AppEvent::Quit)
+                }
+
+                KeyCode::Up | KeyCode::Char('k') if self.selected_review > 0 => {
+                    self.selected_review -= 1;
+                }
+
+                KeyCode::Down | KeyCode::Char('j') => {
+                    let blunders = self.blunders().len();
+                    if self.selected_review + 1 < blunders {
+                        self.selected_review += 1;
+                    }
+                }
+
+                KeyCode::Esc | KeyCode::Char('q') => {
+                    self.selected_button = 0;
+                    self.state = State::Menu;
+                }
 
                 _ => {}
             },
@@ -221,18 +466,668 @@ impl App {
         Ok(())
     }
 
-    /// Handles the tick event of the terminal.
+    /// Keys of [`State::Playing`] while picking tiles for a power-up.
+    fn handle_targeting_key(&mut self, key_event: KeyEvent) -> color_eyre::Result<()> {
+        let Some(targeting) = self.targeting.as_mut() else {
+            return Ok(());
+        };
+        let dim = self.board.dim;
+        match key_event.code {
+            KeyCode::Char('c') if key_event.modifiers == KeyModifiers::CONTROL => {
+                self.events.send(AppEvent::Quit)
+            }
+            KeyCode::Up | KeyCode::Char('k') => targeting.move_cursor(Direction::Up, dim),
+            KeyCode::Down | KeyCode::Char('j') => targeting.move_cursor(Direction::Down, dim),
+            KeyCode::Left | KeyCode::Char('h') => targeting.move_cursor(Direction::Left, dim),
+            KeyCode::Right | KeyCode::Char('l') => targeting.move_cursor(Direction::Right, dim),
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                let Targeting { power, cursor, .. } = *targeting;
+                match targeting.picked {
+                    _ if self.board.size[cursor.0][cursor.1] == 0 => self.show_toast("pick a tile"),
+                    None if power.picks() == 2 => targeting.picked = Some(cursor),
+                    None => self.apply_powerup(power, &[cursor])?,
+                    Some(first) => self.apply_powerup(power, &[first, cursor])?,
+                }
+            }
+            KeyCode::Esc | KeyCode::Char('q') => self.targeting = None,
+            _ => {}
+        }
+        Ok(())
+    }
 
-    ///
+    /// Keys of [`State::Editor`], or of the name prompt while saving a position.
+    fn handle_editor_key(&mut self, key_event: KeyEvent) -> color_eyre::Result<()> {
+        if let Some(name) = self.editor.naming.as_mut() {
+            match key_event.code {
+                KeyCode::Char(c) if name.len() < 32 => name.push(c),
+                KeyCode::Backspace => {
+                    name.pop();
+                }
+                KeyCode::Enter => {
+                    let name = self.editor.naming.take().unwrap_or_default();
+                    self.save_position(name.trim())?;
+                }
+                KeyCode::Esc => self.editor.naming = None,
+                _ => {}
+            }
+            return Ok(());
+        }
 
-    /// The tick event is where you can update the state of your application with any logic that
+        let editor = &mut self.editor;
+        match key_event.code {
+            KeyCode::Char('c') if key_event.modifiers == KeyModifiers::CONTROL => {
+                self.events.send(AppEvent::Quit)
+            }
+            KeyCode::Up | KeyCode::Char('k') => editor.move_cursor(Direction::Up),
+            KeyCode::Down | KeyCode::Char('j') => editor.move_cursor(Direction::Down),
+            KeyCode::Left | KeyCode::Char('h') => editor.move_cursor(Direction::Left),
+            KeyCode::Right | KeyCode::Char('l') => editor.move_cursor(Direction::Right),
+            KeyCode::Char(digit @ '0'..='9') if editor.input.len() < 6 => editor.input.push(digit),
+            KeyCode::Backspace if editor.input.pop().is_none() => editor.set(0),
+            KeyCode::Char('x') | KeyCode::Delete => {
+                editor.input.clear();
+                editor.set(0);
+            }
+            KeyCode::Enter => editor.commit(),
+            KeyCode::Char('+') | KeyCode::Char('=') => {
+                editor.commit();
+                editor.double();
+            }
+            KeyCode::Char('-') => {
+                editor.commit();
+                editor.halve();
+            }
+            KeyCode::Char('w') => {
+                editor.input.clear();
+                editor.toggle_wall();
+            }
+            KeyCode::Char('t') => editor.toggle_to_move(),
+            KeyCode::Char('r') => editor.cycle_spawns(),
+            KeyCode::Char('c') => editor.clear(),
+            KeyCode::Char('[') => editor.selected = editor.selected.saturating_sub(1),
+            KeyCode::Char(']') if editor.selected + 1 < editor.library.len() => {
+                editor.selected += 1;
+            }
+            KeyCode::Char('o') => self.load_position()?,
+            KeyCode::Char('s') => {
+                editor.commit();
+                editor.naming = Some(String::new());
+            }
+            KeyCode::Char('v') => {
+                editor.commit();
+                self.evaluate_position();
+            }
+            KeyCode::Char('p') => {
+                editor.commit();
+                self.play_position();
+            }
+            KeyCode::Esc | KeyCode::Char('q') => {
+                editor.commit();
+                self.state = State::Menu;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
 
+    /// Switches to [`State::Editor`], starting from an empty board of [`App::dim`] and the
+    /// variant picked in the menu the first time, or when either changed.
+    pub fn open_editor(&mut self) -> color_eyre::Result<()> {
+        let board = &self.editor.board;
+        if (board.dim, board.rules.variant) != (self.dim, self.rules.variant) {
+            self.editor = Editor::new(self.dim, self.rules.variant);
+        }
+        self.editor.library = self.db.position_names()?;
+        self.editor.selected = self
+            .editor
+            .selected
+            .min(self.editor.library.len().saturating_sub(1));
+        self.state = State::Editor;
+        Ok(())
+    }
+
+    /// Starts rating the moves from the edited position with the current bot, or with
+    /// expectimax when the bot doesn't rate moves. The ratings show up once [`App::tick`] picks
+    /// them up.
+    pub fn evaluate_position(&mut self) {
+        self.editor.evaluations = None;
+        self.strategy.rate(&self.editor.board);
+    }
+
+    /// Plays on from the edited position in [`Mode::Sandbox`].
+    pub fn play_position(&mut self) {
+        if self.editor.start(0).lost() {
+            self.editor.message = Some("no move is possible from this position".to_string());
+            return;
+        }
+        self.mode = Mode::Sandbox;
+        self.restart();
+    }
+
+    /// Saves the edited position to the library under `name`.
+    pub fn save_position(&mut self, name: &str) -> color_eyre::Result<()> {
+        if name.is_empty() {
+            self.editor.message = Some("a position needs a name".to_string());
+            return Ok(());
+        }
+        let position = SavedPosition {
+            board: self.editor.board.to_text(),
+            spawn_first: self.editor.to_move == ToMove::Spawn,
+            spawns: self.editor.board.rules.spawns.name().to_string(),
+        };
+        self.db.save_position(name, &position)?;
+        self.editor.library = self.db.position_names()?;
+        self.editor.selected = self
+            .editor
+            .library
+            .iter()
+            .position(|n| n == name)
+            .unwrap_or(0);
+        self.editor.message = Some(format!("saved `{name}`"));
+        Ok(())
+    }
+
+    /// Replaces the edited position with the one highlighted in the library.
+    pub fn load_position(&mut self) -> color_eyre::Result<()> {
+        let Some(name) = self.editor.library.get(self.editor.selected).cloned() else {
+            return Ok(());
+        };
+        let position = self.db.load_position(&name)?;
+        let mut board = Board::sized(MAX_DIM, 0);
+        board.rules.variant = self.rules.variant;
+        if let Err(error) = board.load_text(&position.board) {
+            self.editor.message = Some(format!("`{name}` is damaged: {error}"));
+            return Ok(());
+        }
+        board.rules.spawns = Spawns::ALL
+            .into_iter()
+            .find(|s| s.name() == position.spawns)
+            .unwrap_or(Spawns::Random);
+        let editor = &mut self.editor;
+        editor.board = board;
+        editor.to_move = if position.spawn_first {
+            ToMove::Spawn
+        } else {
+            ToMove::Player
+        };
+        editor.cursor = (0, 0);
+        editor.input.clear();
+        editor.evaluations = None;
+        editor.message = Some(format!("loaded `{name}`"));
+        Ok(())
+    }
+
+    /// Goes back to the screen the game was started from, the editor for a sandbox game and the
+    /// menu otherwise.
+    fn leave_game(&mut self) {
+        self.state = match self.mode {
+            Mode::Sandbox => State::Editor,
+            Mode::Classic | Mode::TimeAttack(_) | Mode::Puzzle(_) => State::Menu,
+        };
+    }
+
+    /// Starts a classic game.
+    pub fn start_game(&mut self) {
+        self.mode = Mode::Classic;
+        self.restart();
+    }
+
+    /// Starts a time-attack game with the clock picked in the menu.
+    pub fn start_time_attack(&mut self) {
+        self.mode = Mode::TimeAttack(self.time_limit);
+        self.restart();
+    }
+
+    /// Starts the puzzle at `index` of [`App::puzzles`].
+    pub fn start_puzzle(&mut self, index: usize) {
+        self.mode = Mode::Puzzle(index);
+        self.restart();
+    }
+
+    /// The puzzle being played, if any.
+    pub fn puzzle(&self) -> Option<&Puzzle> {
+        match self.mode {
+            Mode::Puzzle(index) => self.puzzles.get(index),
+            _ => None,
+        }
+    }
+
+    /// Resets the board for another game of [`App::mode`] and switches to [`State::Playing`].
+    pub fn restart(&mut self) {
+        self.state = State::Playing;
+        self.clock = match self.mode {
+            Mode::TimeAttack(seconds) => (f64::from(seconds) * TICK_FPS) as u32,
+            Mode::Classic | Mode::Puzzle(_) | Mode::Sandbox => 0,
+        };
+        self.hint = None;
+        self.autoplay = false;
+        self.bot_moves = 0;
+        self.history.clear();
+        self.warnings.clear();
+        self.inventory = Inventory::default();
+        self.targeting = None;
+        self.powerups.clear();
+        self.cascade = None;
+        self.spin = None;
+        self.toast = None;
+        match self.mode {
+            Mode::Puzzle(index) => self.board = self.puzzles[index].board(),
+            Mode::Sandbox => self.board = self.editor.start(rand::random()),
+            Mode::Classic | Mode::TimeAttack(_) => {
+                self.board.dim = self.dim;
+                self.board.rules = self.rules;
+                self.board.reseed(rand::random());
+                self.board.init_board();
+            }
+        }
+        self.pressure = self.pressure_ticks();
+        self.refresh_meter();
+    }
+
+    /// Ticks a pressure game gives for the next move, see [`Rules::move_seconds`].
+    pub fn pressure_ticks(&self) -> u32 {
+        let rules = &self.board.rules;
+        (rules.move_seconds(self.board.max_tile()) * TICK_FPS) as u32
+    }
+
+    /// Switches to the next win tile in [`TARGETS`] and its default milestones.
+    pub fn cycle_target(&mut self) {
+        let current = TARGETS.iter().position(|&t| t == self.rules.target);
+        let next = current.map_or(0, |i| (i + 1) % TARGETS.len());
+        self.rules.target = TARGETS[next];
+        self.milestones = self.rules.milestones();
+    }
+
+    /// Cells to draw on the playing screen: the cascade pass being shown, or the board.
+    pub fn shown_cells(&self) -> (&[[u32; 4]; 4], &[[Option<Special>; 4]; 4]) {
+        match &self.cascade {
+            Some((steps, ticks)) => {
+                let step = &steps[(*ticks / CASCADE_TICKS) as usize % steps.len()];
+                (&step.size, &step.special)
+            }
+            None => (&self.board.size, &self.board.special),
+        }
+    }
+
+    /// Moves left before the board turns, `None` when it doesn't turn.
+    pub fn moves_to_rotation(&self) -> Option<u32> {
+        let every = self.board.rules.rotate_every;
+        if self.board.rules.rotation == Rotation::Off || every == 0 {
+            return None;
+        }
+        Some(every - self.history.len() as u32 % every)
+    }
+
+    /// Number of milestones reached by the current board.
+    pub fn milestones_reached(&self) -> usize {
+        let max = self.board.max_tile();
+        self.milestones.iter().filter(|&&m| max >= m).count()
+    }
+
+    /// Shows `text` on the playing screen for a couple of seconds.
+    pub fn show_toast(&mut self, text: impl Into<String>) {
+        self.toast = Some((text.into(), TOAST_TICKS));
+    }
+
+    /// Turns the win-probability meter on or off.
+    pub fn toggle_meter(&mut self) {
+        self.estimate = None;
+        self.meter = match self.meter {
+            Some(_) => None,
+            None => Some(Meter::new()),
+        };
+        self.refresh_meter();
+    }
+
+    /// Points the meter at the current board.
+    fn refresh_meter(&mut self) {
+        if let Some(meter) = self.meter.as_mut() {
+            self.estimate = None;
+            meter.update(&self.board);
+        }
+    }
+
+    /// Reviews the finished game on a background thread and switches to [`State::Analysis`].
+    pub fn start_analysis(&mut self) {
+        let history = self.history.clone();
+        self.analysis = None;
+        self.selected_review = 0;
+        self.analysis_job = Some(thread::spawn(move || analysis::analyze(&history)));
+        self.state = State::Analysis;
+    }
+
+    /// Reviews of the moves flagged as blunders, empty until the analysis is done.
+    pub fn blunders(&self) -> Vec<&MoveReview> {
+        self.analysis
+            .iter()
+            .flatten()
+            .filter(|r| r.is_blunder())
+            .collect()
+    }
+
+    /// Plays one move, spawning a tile and ending the game when appropriate.
+    pub fn play(&mut self, direction: Direction) -> color_eyre::Result<()> {
+        let before = self.board.clone();
+        let previous_max = before.max_tile();
+        let Some(steps) = self.board.slide_steps(direction) else {
+            return Ok(());
+        };
+
+        if self.coaching {
+            let warnings = coach::review(&before, direction);
+            if let Some(first) = warnings.first() {
+                self.show_toast(format!("coach: {}", first.message()));
+            }
+            let index = self.history.len();
+            self.warnings
+                .extend(warnings.into_iter().map(|w| (index, w)));
+        }
+
+        let points: u32 = steps.iter().map(|step| step.points).sum();
+        if let Some(last) = steps.iter().rfind(|step| step.points > 0) {
+            self.show_toast(format!("cascade x{} +{points}", last.multiplier));
+        }
+        self.cascade = (!steps.is_empty()).then_some((steps, 0));
+
+        self.hint = None;
+        self.history.push((before, direction));
+        self.pressure = self.pressure_ticks();
+        let moves = self.history.len();
+        match self.mode {
+            Mode::Puzzle(index) => self.puzzles[index].spawn(&mut self.board, moves - 1),
+            Mode::Classic | Mode::TimeAttack(_) | Mode::Sandbox => {
+                adversary::spawn(&mut self.board)
+            }
+        }
+        // The countdown starts over on the move that completes it.
+        if self.moves_to_rotation() == Some(self.board.rules.rotate_every) {
+            let before = self.board.clone();
+            if let Some((clockwise, steps)) = self.board.rotate() {
+                // The tiles fall once the turn has been shown.
+                if let Some(last) = steps.iter().rfind(|step| step.points > 0) {
+                    let points: u32 = steps.iter().map(|step| step.points).sum();
+                    self.show_toast(format!("cascade x{} +{points}", last.multiplier));
+                }
+                self.cascade = (!steps.is_empty()).then_some((steps, 0));
+                self.spin = Some((before, clockwise, 0));
+            }
+        }
+        self.refresh_meter();
+
+        if let Some(puzzle) = self.puzzle() {
+            if puzzle.solved(&self.board) {
+                self.finish(State::Won)?;
+            } else if moves >= puzzle.move_limit as usize || self.board.lost() {
+                self.finish(State::Lost)?;
+            }
+            return Ok(());
+        }
+
+        let max = self.board.max_tile();
+        let mut earned = None;
+        for (i, &milestone) in self.milestones.iter().enumerate() {
+            if max >= milestone && previous_max < milestone {
+                earned = Some((milestone, self.inventory.earn(i)));
+            }
+        }
+        if let Some((milestone, power)) = earned {
+            self.show_toast(format!(
+                "milestone {milestone} reached! +1 {}",
+                power.name()
+            ));
+        }
+
+        if self.board.won() && self.mode == Mode::Classic {
+            self.finish(State::Won)?;
+        } else if self.board.lost() {
+            self.finish(State::Lost)?;
+        }
+        Ok(())
+    }
+
+    /// Starts using `power`: shuffles right away, or lets the player pick the tiles.
+    pub fn use_powerup(&mut self, power: PowerUp) -> color_eyre::Result<()> {
+        if self.inventory.count(power) == 0 {
+            self.show_toast(format!("no {} left", power.name()));
+            return Ok(());
+        }
+        self.autoplay = false;
+        match power.picks() {
+            0 => self.apply_powerup(power, &[]),
+            _ => {
+                self.targeting = Some(Targeting::new(power));
+                Ok(())
+            }
+        }
+    }
+
+    /// Applies `power` to the picked `cells` and records it in [`App::powerups`].
+    fn apply_powerup(
+        &mut self,
+        power: PowerUp,
+        cells: &[(usize, usize)],
+    ) -> color_eyre::Result<()> {
+        if let Err(message) = powerup::apply(&mut self.board, power, cells) {
+            self.show_toast(message);
+            return Ok(());
+        }
+        self.inventory.take(power);
+        self.targeting = None;
+        self.powerups.push((self.history.len(), power));
+        self.hint = None;
+        self.refresh_meter();
+        self.show_toast(format!("used {}", power.name()));
+        if self.board.lost() {
+            self.finish(State::Lost)?;
+        }
+        Ok(())
+    }
+
+    /// Ends the game in `state` and saves its score to the leaderboard of [`App::mode`] and the
+    /// difficulty, or to the powered one when a power-up was used.
+    fn finish(&mut self, state: State) -> color_eyre::Result<()> {
+        let solved = matches!(state, State::Won);
+        self.state = state;
+        self.autoplay = false;
+        self.targeting = None;
+        let score = self.board.calculate_score();
+        let difficulty = self.board.rules.difficulty;
+        let rules = self.board.rules.signature(self.board.dim);
+        if self.bot_moves > 0 {
+            return Ok(());
+        }
+        if let (Some(board), false) = (self.mode.leaderboard(), self.powerups.is_empty()) {
+            let used = self.powerups.len() as u32;
+            self.db
+                .record_powered(&board, &rules, difficulty, score, used)?;
+            return Ok(());
+        }
+        match self.mode {
+            Mode::Classic => self.db.update_score(&rules, difficulty, score)?,
+            Mode::TimeAttack(seconds) => self
+                .db
+                .record_time_attack(seconds, &rules, difficulty, score)?,
+            Mode::Puzzle(index) => {
+                let moves = solved.then_some(self.history.len() as u32);
+                self.db.record_puzzle(&self.puzzles[index].id, moves)?;
+            }
+            Mode::Sandbox => {}
+        }
+        Ok(())
+    }
+
+    /// Whole seconds left on the time-attack clock, rounded up.
+    pub fn seconds_left(&self) -> u32 {
+        (f64::from(self.clock) / TICK_FPS).ceil() as u32
+    }
+
+    /// Handles the tick event of the terminal.
+    ///
+    /// The tick event is where you can update the state of your application with any logic that
     /// needs to be updated at a fixed frame rate. E.g. polling a server, updating an animation.
+    pub fn tick(&mut self) -> color_eyre::Result<()> {
+        if self
+            .analysis_job
+            .as_ref()
+            .is_some_and(|job| job.is_finished())
+        {
+            let job = self.analysis_job.take().unwrap();
+            self.analysis = Some(job.join().unwrap_or_default());
+        }
 
-    pub fn tick(&self) {}
+        if let Some(meter) = self.meter.as_mut() {
+            self.estimate = meter.poll();
+        }
 
-    /// Set running to false to quit the application.
+        if let Some((_, ticks)) = self.toast.as_mut() {
+            *ticks = ticks.saturating_sub(1);
+            if *ticks == 0 {
+                self.toast = None;
+            }
+        }
+
+        if let Some((_, _, ticks)) = self.spin.as_mut() {
+            *ticks += 1;
+            if *ticks >= SPIN_TICKS {
+                self.spin = None;
+            }
+        } else if let Some((steps, ticks)) = self.cascade.as_mut() {
+            *ticks += 1;
+            if *ticks >= steps.len() as u32 * CASCADE_TICKS {
+                self.cascade = None;
+            }
+        }
+
+        if let Some(ratings) = self.strategy.ratings(&self.editor.board) {
+            self.editor.evaluations = Some(ratings);
+        }
+
+        if !matches!(self.state, State::Playing) {
+            return Ok(());
+        }
 
+        if let Some(direction) = self.strategy.poll(&self.board) {
+            if self.autoplay && self.targeting.is_none() {
+                self.bot_moves += 1;
+                return self.play(direction);
+            }
+            self.hint = Some(direction);
+        }
+
+        if let Mode::TimeAttack(_) = self.mode {
+            self.clock = self.clock.saturating_sub(1);
+            if self.clock == 0 {
+                return self.finish(State::Lost);
+            }
+        }
+
+        // The clock stops while the board turns or a power-up waits for its cell.
+        if self.board.rules.pressure != Pressure::Off
+            && self.spin.is_none()
+            && self.targeting.is_none()
+        {
+            self.pressure = self.pressure.saturating_sub(1);
+            if self.pressure == 0 {
+                return self.hurry();
+            }
+        }
+
+        if !self.autoplay || self.targeting.is_some() {
+            return Ok(());
+        }
+
+        self.ticks += 1;
+        if self.ticks >= AUTOPLAY_TICKS && !self.strategy.is_thinking() {
+            self.ticks = 0;
+            self.strategy.ask(&self.board);
+        }
+
+        Ok(())
+    }
+
+    /// Switches to the next bot in [`STRATEGIES`], skipping the ones that can't be loaded with a
+    /// toast saying why.
+    pub fn next_strategy(&mut self) {
+        for _ in 0..STRATEGIES.len() {
+            self.strategy_index = (self.strategy_index + 1) % STRATEGIES.len();
+            let name = STRATEGIES[self.strategy_index];
+            match strategy::by_name(name) {
+                Ok(next) => {
+                    self.strategy = Thinker::new(next);
+                    self.hint = None;
+                    return;
+                }
+                Err(error) => self.show_toast(format!("{name}: {error}")),
+            }
+        }
+    }
+
+    /// One line telling how much of the game autoplay played, for the end-of-game popup.
+    pub fn autoplay_summary(&self) -> Option<String> {
+        match self.bot_moves {
+            0 => None,
+            moves => Some(format!(
+                "Autoplay: {moves} of {} moves, not recorded",
+                self.history.len()
+            )),
+        }
+    }
+
+    /// Moves a pressure game on when the player took too long, as set by [`Rules::pressure`].
+    fn hurry(&mut self) -> color_eyre::Result<()> {
+        match self.board.rules.pressure {
+            Pressure::Off => {}
+            Pressure::Spawn => {
+                adversary::spawn(&mut self.board);
+                self.hint = None;
+                self.pressure = self.pressure_ticks();
+                self.show_toast("too slow: an extra tile");
+                self.refresh_meter();
+                if self.board.lost() {
+                    return self.finish(State::Lost);
+                }
+            }
+            Pressure::Move => {
+                // Drawn apart from the board's generator, so the spawns a seed replays stay put.
+                let moves = self.board.legal_moves();
+                let Some(&direction) = moves.choose(&mut rand::rng()) else {
+                    return Ok(());
+                };
+                self.show_toast(format!("too slow: {} played for you", direction.arrow()));
+                self.play(direction)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// One line summing up the coach's warnings for the end-of-game popup.
+    pub fn coach_summary(&self) -> Option<String> {
+        if !self.coaching {
+            return None;
+        }
+        if self.warnings.is_empty() {
+            return Some("Coach: clean game, no warnings!".to_string());
+        }
+        let counts: Vec<String> = Warning::ALL
+            .iter()
+            .map(|&kind| {
+                let n = self.warnings.iter().filter(|(_, w)| *w == kind).count();
+                format!("{n} {}", kind.label())
+            })
+            .collect();
+        Some(format!("Coach: {}", counts.join(", ")))
+    }
+
+    /// One line listing the power-ups used this game for the end-of-game popup.
+    pub fn powerup_summary(&self) -> Option<String> {
+        if self.powerups.is_empty() {
+            return None;
+        }
+        let used: Vec<&str> = self.powerups.iter().map(|(_, p)| p.name()).collect();
+        Some(format!("Power-ups: {}, scored apart", used.join(", ")))
+    }
+
+    /// Set running to false to quit the application.
     pub fn quit(&mut self) {
         self.running = false;
     }
@@ -0,0 +1,282 @@
+//! C API over [`Board`], built into the `cdylib`. See `include/r2048.h`, generated from this file
+//! by cbindgen, for the C side and `examples/c/play.c` for a small program using it.
+//!
+//! Every function taking an `R2048Game` pointer accepts null and treats it as a no-op, returning
+//! `0` where a value is expected.
+
+use crate::board::{Board, Direction};
+use std::ffi::{CStr, c_char, c_int};
+use std::ptr;
+
+/// Bumped whenever a function signature or the serialized format changes.
+pub const R2048_ABI_VERSION: u32 = 1;
+
+/// Opaque game handle.
+pub struct R2048Game {
+    board: Board,
+}
+
+/// Returns [`R2048_ABI_VERSION`].
+#[unsafe(no_mangle)]
+pub extern "C" fn r2048_abi_version() -> u32 {
+    R2048_ABI_VERSION
+}
+
+/// Creates a game whose spawns are drawn from `seed`, with the two starting tiles placed.
+/// Release it with [`r2048_free`].
+#[unsafe(no_mangle)]
+pub extern "C" fn r2048_new(seed: u64) -> *mut R2048Game {
+    let mut board = Board::seeded(seed);
+    board.init_board();
+    Box::into_raw(Box::new(R2048Game { board }))
+}
+
+/// Destroys a game created by [`r2048_new`] or [`r2048_deserialize`].
+///
+/// # Safety
+///
+/// `game` must be null or a pointer returned by this library that was not freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn r2048_free(game: *mut R2048Game) {
+    if !game.is_null() {
+        drop(unsafe { Box::from_raw(game) });
+    }
+}
+
+/// Restarts the spawn sequence from `seed` without touching the cells.
+///
+/// # Safety
+///
+/// `game` must be null or a live game handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn r2048_seed(game: *mut R2048Game, seed: u64) {
+    if let Some(game) = unsafe { game.as_mut() } {
+        game.board.reseed(seed);
+    }
+}
+
+/// Clears the board and places two new starting tiles.
+///
+/// # Safety
+///
+/// `game` must be null or a live game handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn r2048_reset(game: *mut R2048Game) {
+    if let Some(game) = unsafe { game.as_mut() } {
+        game.board.init_board();
+    }
+}
+
+/// Slides the tiles (`0` up, `1` down, `2` left, `3` right) and spawns a tile if anything moved.
+/// Returns `1` if the board changed, `0` if the move was a no-op and `-1` for an unknown
+/// direction.
+///
+/// # Safety
+///
+/// `game` must be null or a live game handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn r2048_move(game: *mut R2048Game, direction: c_int) -> c_int {
+    let Some(game) = (unsafe { game.as_mut() }) else {
+        return 0;
+    };
+    let Some(direction) = usize::try_from(direction)
+        .ok()
+        .and_then(Direction::from_index)
+    else {
+        return -1;
+    };
+    if game.board.slide(direction) {
+        game.board.spawn_one_random();
+        1
+    } else {
+        0
+    }
+}
+
+/// Value of the cell at `row`, `col`, `0` when empty or out of range.
+///
+/// # Safety
+///
+/// `game` must be null or a live game handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn r2048_cell(game: *const R2048Game, row: u32, col: u32) -> u32 {
+    unsafe { game.as_ref() }
+        .and_then(|game| {
+            game.board
+                .size
+                .get(row as usize)?
+                .get(col as usize)
+                .copied()
+        })
+        .unwrap_or(0)
+}
+
+/// Sum of the tiles on the board.
+///
+/// # Safety
+///
+/// `game` must be null or a live game handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn r2048_score(game: *const R2048Game) -> u32 {
+    unsafe { game.as_ref() }.map_or(0, |game| game.board.calculate_score())
+}
+
+/// Sum of every merge this game, the classic 2048 score.
+///
+/// # Safety
+///
+/// `game` must be null or a live game handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn r2048_merge_score(game: *const R2048Game) -> u32 {
+    unsafe { game.as_ref() }.map_or(0, |game| game.board.merge_score)
+}
+
+/// `1` once no move can change the board, `0` otherwise.
+///
+/// # Safety
+///
+/// `game` must be null or a live game handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn r2048_game_over(game: *const R2048Game) -> c_int {
+    unsafe { game.as_ref() }.map_or(0, |game| c_int::from(game.board.lost()))
+}
+
+/// `1` if a 2048 tile or a bigger one is on the board, `0` otherwise.
+///
+/// # Safety
+///
+/// `game` must be null or a live game handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn r2048_won(game: *const R2048Game) -> c_int {
+    unsafe { game.as_ref() }.map_or(0, |game| c_int::from(game.board.won()))
+}
+
+/// Writes the state as a NUL-terminated string into `buf` and returns its length without the
+/// NUL. If `len` is too small nothing is written, call again with a buffer of at least the
+/// returned length plus one. The spawn sequence is not saved.
+///
+/// # Safety
+///
+/// `game` must be null or a live game handle, and `buf` must be null or valid for `len` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn r2048_serialize(
+    game: *const R2048Game,
+    buf: *mut c_char,
+    len: usize,
+) -> usize {
+    let Some(game) = (unsafe { game.as_ref() }) else {
+        return 0;
+    };
+    let text = game.board.to_text();
+    if !buf.is_null() && text.len() < len {
+        unsafe {
+            ptr::copy_nonoverlapping(text.as_ptr(), buf.cast(), text.len());
+            *buf.add(text.len()) = 0;
+        }
+    }
+    text.len()
+}
+
+/// Creates a game from a string written by [`r2048_serialize`], drawing future spawns from
+/// `seed`. Returns null if the string is malformed.
+///
+/// # Safety
+///
+/// `text` must be null or a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn r2048_deserialize(text: *const c_char, seed: u64) -> *mut R2048Game {
+    if text.is_null() {
+        return ptr::null_mut();
+    }
+    let Ok(text) = unsafe { CStr::from_ptr(text) }.to_str() else {
+        return ptr::null_mut();
+    };
+    let mut board = Board::seeded(seed);
+    match board.load_text(text) {
+        Ok(()) => Box::into_raw(Box::new(R2048Game { board })),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    /// The string [`r2048_serialize`] writes for `game`.
+    fn serialize(game: *const R2048Game) -> String {
+        let len = unsafe { r2048_serialize(game, ptr::null_mut(), 0) };
+        let mut buf = vec![0 as c_char; len + 1];
+        assert_eq!(
+            unsafe { r2048_serialize(game, buf.as_mut_ptr(), buf.len()) },
+            len
+        );
+        unsafe { CStr::from_ptr(buf.as_ptr()) }
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn reports_its_version() {
+        assert_eq!(r2048_abi_version(), R2048_ABI_VERSION);
+    }
+
+    #[test]
+    fn games_round_trip_through_text() {
+        for text in [
+            "2,#,4b,0/8,*,0,16/o,32d,0,2/4,0,#,64 12 3",
+            "2,4,8/0,#,16/32,0,64 40",
+        ] {
+            let text = CString::new(text).unwrap();
+            let game = unsafe { r2048_deserialize(text.as_ptr(), 7) };
+            assert!(!game.is_null());
+            assert_eq!(serialize(game), text.to_str().unwrap());
+            unsafe { r2048_free(game) };
+        }
+
+        let game = r2048_new(7);
+        let saved = CString::new(serialize(game)).unwrap();
+        let copy = unsafe { r2048_deserialize(saved.as_ptr(), 7) };
+        for row in 0..4 {
+            for col in 0..4 {
+                assert_eq!(unsafe { r2048_cell(game, row, col) }, unsafe {
+                    r2048_cell(copy, row, col)
+                });
+            }
+        }
+        unsafe {
+            r2048_free(game);
+            r2048_free(copy);
+        }
+    }
+
+    #[test]
+    fn short_buffers_are_left_alone() {
+        let game = r2048_new(7);
+        let len = unsafe { r2048_serialize(game, ptr::null_mut(), 0) };
+        let mut buf = vec![1 as c_char; len];
+        assert_eq!(unsafe { r2048_serialize(game, buf.as_mut_ptr(), len) }, len);
+        assert!(buf.iter().all(|&c| c == 1));
+        unsafe { r2048_free(game) };
+    }
+
+    #[test]
+    fn bad_input_is_refused() {
+        let bad = CString::new("2,2/2 0").unwrap();
+        assert!(unsafe { r2048_deserialize(bad.as_ptr(), 0) }.is_null());
+        assert!(unsafe { r2048_deserialize(ptr::null(), 0) }.is_null());
+
+        let game = r2048_new(7);
+        assert_eq!(unsafe { r2048_move(game, 4) }, -1);
+        assert_eq!(unsafe { r2048_move(game, -1) }, -1);
+        unsafe { r2048_free(game) };
+
+        assert_eq!(unsafe { r2048_move(ptr::null_mut(), 0) }, 0);
+        assert_eq!(
+            unsafe { r2048_serialize(ptr::null(), ptr::null_mut(), 0) },
+            0
+        );
+        assert_eq!(unsafe { r2048_cell(ptr::null(), 0, 0) }, 0);
+    }
+}
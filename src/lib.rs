@@ -1,3 +1,22 @@
+pub mod ai {
+    pub mod adversary;
+    pub mod analysis;
+    pub mod coach;
+    pub mod meter;
+    pub mod ntuple;
+    pub mod search;
+    pub mod solver;
+    pub mod strategy;
+    pub mod thinker;
+}
+pub mod cli {
+    pub mod arena;
+    pub mod args;
+    pub mod export;
+    pub mod simulate;
+    pub mod solve;
+    pub mod train;
+}
 pub mod events {
     pub mod app;
     pub mod event;
@@ -8,10 +27,16 @@ pub mod tui {
 
 pub mod board;
 pub mod db;
+pub mod editor;
+pub mod env;
+pub mod ffi;
+pub mod powerup;
+pub mod puzzle;
 
 pub mod decoration {
 
-    use ratatui::style::Color;
+    use crate::board::Variant;
+    use ratatui::style::{Color, Style, Stylize};
 
     #[inline(always)]
     pub fn get_background_color(value: u32) -> Color {
@@ -30,4 +55,33 @@ pub mod decoration {
             _ => Color::Black,
         }
     }
+
+    /// The classic tile whose colours `value` borrows in `variant`: the one of the same rank on
+    /// the variant's ladder, counting Threes from its 3.
+    pub fn classic_shade(variant: Variant, value: u32) -> u32 {
+        let rank = match variant {
+            Variant::Threes => variant.rank(value).saturating_sub(2),
+            _ => variant.rank(value),
+        };
+        2u32.checked_shl(rank as u32).unwrap_or(0)
+    }
+
+    pub fn tile_color(variant: Variant, value: u32) -> Color {
+        match (variant, value) {
+            (Variant::Threes, 1) => Color::Rgb(102, 204, 255), // #66ccff
+            (Variant::Threes, 2) => Color::Rgb(255, 102, 128), // #ff6680
+            _ => get_background_color(classic_shade(variant, value)),
+        }
+    }
+
+    /// Style of the number written on a tile.
+    pub fn tile_style(variant: Variant, value: u32) -> Style {
+        match (variant, value) {
+            (Variant::Threes, 1 | 2) => Style::new().white().bold(),
+            _ => match classic_shade(variant, value) {
+                2 | 4 => Style::new().blue(),
+                _ => Style::new().black().bold(),
+            },
+        }
+    }
 }
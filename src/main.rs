@@ -1,9 +1,108 @@
+use color_eyre::eyre::{bail, eyre};
+use r2048::board::{Difficulty, MAX_DIM, Pressure, Rotation, Rules, Specials, Variant, Walls};
+use r2048::cli::{self, args::Args};
 use r2048::events::app::App;
+use r2048::puzzle;
+use std::path::Path;
 
 fn main() -> color_eyre::Result<()> {
     color_eyre::install()?;
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        None => {}
+        Some(flag) if flag.starts_with("--") => {}
+        Some("arena") => return cli::arena::run(&args[1..]),
+        Some("export-dataset") => return cli::export::run(&args[1..]),
+        Some("simulate") => return cli::simulate::run(&args[1..]),
+        Some("solve") => return cli::solve::run(&args[1..]),
+        Some("train") => return cli::train::run(&args[1..]),
+        Some(other) => bail!("unknown command `{other}`"),
+    }
+
+    let args = Args::new(&args);
+    let dim: usize = args.parse("--size", MAX_DIM)?;
+    let variant = match args.value("--variant") {
+        Some(variant) => variant.parse::<Variant>().map_err(|e| eyre!(e))?,
+        None => Variant::Classic,
+    };
+    let target: u32 = match args.value("--target") {
+        Some(_) => args.parse("--target", 0)?,
+        None => Rules {
+            variant,
+            ..Rules::default()
+        }
+        .goal(),
+    };
+    if target < 8 || !variant.is_tile(target) {
+        bail!(
+            "--target must be a {} tile of at least 8, not {target}",
+            variant.name()
+        );
+    }
+    let walls = match args.value("--walls") {
+        Some(walls) => walls.parse::<Walls>().map_err(|e| eyre!(e))?,
+        None => Walls::Off,
+    };
+    let difficulty = match args.value("--difficulty") {
+        Some(difficulty) => difficulty.parse::<Difficulty>().map_err(|e| eyre!(e))?,
+        None => Difficulty::Normal,
+    };
+    let rotation = match args.value("--rotate") {
+        Some(rotation) => rotation.parse::<Rotation>().map_err(|e| eyre!(e))?,
+        None => Rotation::Off,
+    };
+    let rotate_every: u32 = args.parse("--rotate-every", Rules::default().rotate_every)?;
+    if rotate_every == 0 {
+        bail!("--rotate-every must be at least 1");
+    }
+    let pressure = match args.value("--pressure") {
+        Some(pressure) => pressure.parse::<Pressure>().map_err(|e| eyre!(e))?,
+        None => Pressure::Off,
+    };
+    let pressure_seconds: u32 =
+        args.parse("--pressure-seconds", Rules::default().pressure_seconds)?;
+    if pressure_seconds == 0 {
+        bail!("--pressure-seconds must be at least 1");
+    }
+    let evil_depth: u8 = args.parse("--evil-depth", Rules::default().evil_depth)?;
+    if !(1..=2).contains(&evil_depth) {
+        bail!("--evil-depth must be 1 or 2, not {evil_depth}");
+    }
+    let specials = match args.value("--specials") {
+        Some(specials) => specials.parse::<Specials>().map_err(|e| eyre!(e))?,
+        None => Specials::default(),
+    };
+
+    let mut app = App::new();
+    app.dim = dim.clamp(2, MAX_DIM);
+    app.rules.target = target;
+    app.rules.variant = variant;
+    app.rules.walls = walls;
+    app.rules.wrap = args.has("--wrap");
+    app.rules.gravity = args.has("--gravity");
+    app.rules.rotation = rotation;
+    app.rules.rotate_every = rotate_every;
+    app.rules.pressure = pressure;
+    app.rules.pressure_seconds = pressure_seconds;
+    app.rules.specials = specials;
+    app.rules.difficulty = difficulty;
+    app.rules.evil_depth = evil_depth;
+    (app.puzzles, app.skipped_packs) = puzzle::load_all();
+    if let Some(path) = args.value("--puzzles") {
+        app.puzzles.extend(puzzle::load_pack(Path::new(path))?);
+    }
+    app.milestones = match args.list::<u32>("--milestones")? {
+        Some(mut milestones) => {
+            milestones.sort_unstable();
+            milestones.dedup();
+            milestones
+        }
+        None => app.rules.milestones(),
+    };
+
     let terminal = ratatui::init();
-    App::new().run(terminal)?;
+    app.run(terminal)?;
     ratatui::restore();
     Ok(())
 }
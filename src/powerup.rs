@@ -0,0 +1,201 @@
+//! Power-ups: a few one-off helpers earned at milestones, used from the playing screen.
+
+use crate::board::{Board, Direction};
+use rand::seq::SliceRandom;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerUp {
+    /// Exchanges two tiles.
+    Swap,
+    /// Removes one tile.
+    Delete,
+    /// Scatters every tile over the free cells at random.
+    Shuffle,
+}
+
+impl PowerUp {
+    /// In the order they are earned, and the keys `1`, `2` and `3` use them.
+    pub const ALL: [PowerUp; 3] = [PowerUp::Swap, PowerUp::Delete, PowerUp::Shuffle];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PowerUp::Swap => "swap",
+            PowerUp::Delete => "delete",
+            PowerUp::Shuffle => "shuffle",
+        }
+    }
+
+    /// Number of tiles to pick before it takes effect.
+    pub fn picks(self) -> usize {
+        match self {
+            PowerUp::Swap => 2,
+            PowerUp::Delete => 1,
+            PowerUp::Shuffle => 0,
+        }
+    }
+}
+
+/// Power-ups held by the player.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Inventory {
+    /// How many of each, indexed like [`PowerUp::ALL`].
+    pub counts: [u32; 3],
+}
+
+impl Inventory {
+    pub fn count(&self, power: PowerUp) -> u32 {
+        self.counts[power as usize]
+    }
+
+    /// Adds the power-up earned for the milestone at `index`, going round [`PowerUp::ALL`].
+    pub fn earn(&mut self, index: usize) -> PowerUp {
+        let power = PowerUp::ALL[index % PowerUp::ALL.len()];
+        self.counts[power as usize] += 1;
+        power
+    }
+
+    /// Takes one `power` out, if there is one.
+    pub fn take(&mut self, power: PowerUp) -> bool {
+        let count = &mut self.counts[power as usize];
+        if *count == 0 {
+            return false;
+        }
+        *count -= 1;
+        true
+    }
+}
+
+/// Tiles being picked for a power-up on the playing screen. Sliding is paused meanwhile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Targeting {
+    pub power: PowerUp,
+    pub cursor: (usize, usize),
+    /// Tile picked first, for a swap.
+    pub picked: Option<(usize, usize)>,
+}
+
+impl Targeting {
+    pub fn new(power: PowerUp) -> Self {
+        Self {
+            power,
+            cursor: (0, 0),
+            picked: None,
+        }
+    }
+
+    /// Moves the cursor one cell on a `dim`×`dim` board, staying on it.
+    pub fn move_cursor(&mut self, direction: Direction, dim: usize) {
+        let (row, col) = self.cursor;
+        self.cursor = match direction {
+            Direction::Up => (row.saturating_sub(1), col),
+            Direction::Down => ((row + 1).min(dim - 1), col),
+            Direction::Left => (row, col.saturating_sub(1)),
+            Direction::Right => (row, (col + 1).min(dim - 1)),
+        };
+    }
+}
+
+/// Applies `power` to `board` with the tiles picked for it, see [`PowerUp::picks`]. Fails
+/// with a message for the player when a pick isn't a tile.
+pub fn apply(board: &mut Board, power: PowerUp, cells: &[(usize, usize)]) -> Result<(), String> {
+    if cells.len() != power.picks() {
+        return Err(format!("{} needs {} tiles", power.name(), power.picks()));
+    }
+    if let Some(&(row, col)) = cells.iter().find(|&&(r, c)| board.size[r][c] == 0) {
+        return Err(format!("no tile at row {}, column {}", row + 1, col + 1));
+    }
+    match power {
+        PowerUp::Swap => {
+            let [(r1, c1), (r2, c2)] = [cells[0], cells[1]];
+            if (r1, c1) == (r2, c2) {
+                return Err("pick two different tiles".to_string());
+            }
+            let first = (board.size[r1][c1], board.special[r1][c1]);
+            (board.size[r1][c1], board.special[r1][c1]) =
+                (board.size[r2][c2], board.special[r2][c2]);
+            (board.size[r2][c2], board.special[r2][c2]) = first;
+        }
+        PowerUp::Delete => {
+            let (row, col) = cells[0];
+            board.size[row][col] = 0;
+            board.special[row][col] = None;
+        }
+        PowerUp::Shuffle => {
+            let open: Vec<(usize, usize)> = (0..board.dim)
+                .flat_map(|row| (0..board.dim).map(move |col| (row, col)))
+                .filter(|&(row, col)| !board.walls[row][col])
+                .collect();
+            let mut tiles: Vec<_> = open
+                .iter()
+                .map(|&(row, col)| (board.size[row][col], board.special[row][col]))
+                .collect();
+            // Off the board's own generator, so the spawns still follow the game's seed.
+            tiles.shuffle(&mut rand::rng());
+            for (&(row, col), (value, special)) in open.iter().zip(tiles) {
+                board.size[row][col] = value;
+                board.special[row][col] = special;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    fn board(text: &str) -> Board {
+        let mut board = Board::seeded(5);
+        board.load_text(text).unwrap();
+        board
+    }
+
+    #[test]
+    fn milestones_hand_out_each_power_in_turn() {
+        let mut inventory = Inventory::default();
+        let earned: Vec<PowerUp> = (0..4).map(|i| inventory.earn(i)).collect();
+        assert_eq!(
+            earned,
+            [
+                PowerUp::Swap,
+                PowerUp::Delete,
+                PowerUp::Shuffle,
+                PowerUp::Swap
+            ]
+        );
+        assert_eq!(inventory.counts, [2, 1, 1]);
+        assert!(inventory.take(PowerUp::Delete));
+        assert!(!inventory.take(PowerUp::Delete));
+    }
+
+    #[test]
+    fn swap_and_delete_need_tiles() {
+        let mut b = board("2,4/0,8 0");
+        assert!(apply(&mut b, PowerUp::Swap, &[(0, 0), (1, 0)]).is_err());
+        assert!(apply(&mut b, PowerUp::Swap, &[(0, 0), (0, 0)]).is_err());
+        apply(&mut b, PowerUp::Swap, &[(0, 0), (1, 1)]).unwrap();
+        assert_eq!(b.to_text(), "8,4/0,2 0");
+        assert!(apply(&mut b, PowerUp::Delete, &[(1, 0)]).is_err());
+        apply(&mut b, PowerUp::Delete, &[(0, 1)]).unwrap();
+        assert_eq!(b.to_text(), "8,0/0,2 0");
+    }
+
+    #[test]
+    fn shuffle_keeps_the_tiles_walls_and_spawns() {
+        let mut b = board("2,#,4,0/8,*,0,16/0,32,0,2/4,0,#,64 0");
+        let before = b.clone();
+        apply(&mut b, PowerUp::Shuffle, &[]).unwrap();
+        assert_eq!(b.walls, before.walls);
+        let tiles = |b: &Board| {
+            let mut tiles: Vec<_> = (0..4)
+                .flat_map(|row| (0..4).map(move |col| (row, col)))
+                .map(|(row, col)| (b.size[row][col], b.special[row][col]))
+                .collect();
+            tiles.sort_by_key(|&(value, special)| (value, special.is_some()));
+            tiles
+        };
+        assert_eq!(tiles(&b), tiles(&before));
+        assert_eq!(b.rng.random::<u64>(), before.clone().rng.random::<u64>());
+    }
+}
@@ -0,0 +1,326 @@
+//! Puzzles: a set starting position, a fixed list of spawns, a goal and a move limit.
+//!
+//! Puzzles are written in packs of plain text, one `key: value` per line and a blank line
+//! between puzzles. Lines starting with `#` are comments.
+//!
+//! ```text
+//! name: Warm-up
+//! board: 2,2,0,0/0,0,0,0/0,0,0,0/0,0,0,0
+//! spawns: 2@3,3 4
+//! goal: reach 8
+//! moves: 3
+//! ```
+//!
+//! `board` uses the format of [`Board::to_text`], `#` marking a wall. After each move the next entry of `spawns`
+//! is placed: `2@3,3` puts a 2 at row 3, column 3, and a bare value (or a taken cell) goes to
+//! the first empty cell in reading order. Once the list runs out nothing spawns. `goal` is one
+//! of `reach <tile>`, `tiles <n>` (at most `n` tiles left on the board) or `score <points>`
+//! (merge score).
+
+use crate::board::Board;
+use crate::db;
+use color_eyre::eyre::{WrapErr, bail, eyre};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The pack shipped with the game.
+const BUILTIN: &str = "\
+name: First steps
+board: 2,2,0,0/0,0,0,0/0,0,0,0/0,0,0,0
+spawns: 2 2 2 2
+goal: reach 8
+moves: 5
+
+name: Line up
+board: 4,0,0,4/0,0,0,0/0,0,0,0/4,0,0,4
+spawns: 2@0,0 2@0,0
+goal: reach 16
+moves: 3
+
+name: Spring cleaning
+board: 2,2,4,8/2,2,4,8/0,0,0,0/0,0,0,0
+spawns:
+goal: tiles 1
+moves: 4
+
+name: Cascade
+board: 2,2,4,8/0,0,0,0/0,0,0,0/0,0,0,16
+spawns: 2@1,0 2@1,0 2@1,0 2@1,0 2@1,0
+goal: reach 32
+moves: 5
+
+name: Chain reaction
+board: 2,2,4,8/16,0,0,0/0,0,0,0/0,0,0,0
+spawns: 2@3,3 2@3,3 2@3,3 2@3,3
+goal: score 60
+moves: 4
+
+name: Tight squeeze
+board: 4,2,4,2/2,4,2,4/4,2,4,2/2,4,8,8
+spawns: 2@0,0
+goal: reach 32
+moves: 5
+
+name: Small world
+board: 2,4,0/4,2,0/8,0,0
+spawns: 2 4 2 2
+goal: reach 16
+moves: 3
+
+name: Patience
+board: 64,32,16,8/0,0,0,4/0,0,0,0/0,0,0,0
+spawns: 4@0,3 2@1,0 2@1,0 2@1,0 2@1,0 2@1,0
+goal: reach 128
+moves: 6
+";
+
+/// What a puzzle asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Goal {
+    /// Get a tile of at least this value.
+    Reach(u32),
+    /// Leave at most this many tiles on the board.
+    Tiles(usize),
+    /// Score at least this many points from merges.
+    Score(u32),
+}
+
+impl Goal {
+    pub fn describe(self) -> String {
+        match self {
+            Goal::Reach(tile) => format!("reach {tile}"),
+            Goal::Tiles(1) => "leave a single tile".to_string(),
+            Goal::Tiles(n) => format!("leave {n} tiles or fewer"),
+            Goal::Score(points) => format!("score {points} points"),
+        }
+    }
+}
+
+/// One tile of a puzzle's spawn list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Spawn {
+    pub value: u32,
+    /// Where it lands, the first empty cell when `None` or taken.
+    pub cell: Option<(usize, usize)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Puzzle {
+    /// Pack and name, the key progress is saved under.
+    pub id: String,
+    pub name: String,
+    pub start: Board,
+    pub spawns: Vec<Spawn>,
+    pub goal: Goal,
+    pub move_limit: u32,
+}
+
+impl Puzzle {
+    /// The starting position.
+    pub fn board(&self) -> Board {
+        self.start.clone()
+    }
+
+    /// Places the spawn following move number `index`, counting from 0.
+    pub fn spawn(&self, board: &mut Board, index: usize) {
+        let Some(spawn) = self.spawns.get(index) else {
+            return;
+        };
+        let cell = spawn
+            .cell
+            .filter(|&(row, col)| board.size[row][col] == 0 && !board.walls[row][col])
+            .or_else(|| board.empty_cells().first().copied());
+        if let Some((row, col)) = cell {
+            board.size[row][col] = spawn.value;
+        }
+    }
+
+    pub fn solved(&self, board: &Board) -> bool {
+        match self.goal {
+            Goal::Reach(tile) => board.max_tile() >= tile,
+            Goal::Tiles(n) => board.size.iter().flatten().filter(|&&v| v != 0).count() <= n,
+            Goal::Score(points) => board.merge_score >= points,
+        }
+    }
+}
+
+/// Reads every puzzle in `text`, naming their ids after `pack`.
+pub fn parse_pack(pack: &str, text: &str) -> color_eyre::Result<Vec<Puzzle>> {
+    let mut puzzles = Vec::new();
+    let blocks = text.split("\n\n").map(str::trim).filter(|b| !b.is_empty());
+    for (index, block) in blocks.enumerate() {
+        let puzzle = parse_puzzle(pack, block)
+            .wrap_err_with(|| format!("puzzle {} of pack `{pack}`", index + 1))?;
+        puzzles.push(puzzle);
+    }
+    Ok(puzzles)
+}
+
+fn parse_puzzle(pack: &str, block: &str) -> color_eyre::Result<Puzzle> {
+    let (mut name, mut start, mut spawns, mut goal, mut move_limit) =
+        (None, None, None, None, None);
+    for line in block.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = line
+            .split_once(':')
+            .ok_or_else(|| eyre!("expected `key: value`, got `{line}`"))?;
+        let value = value.trim();
+        match key.trim() {
+            "name" => name = Some(value.to_string()),
+            "board" => {
+                let mut board = Board::seeded(0);
+                board.load_text(value).map_err(|e| eyre!(e))?;
+                start = Some(board);
+            }
+            "spawns" => spawns = Some(parse_spawns(value)?),
+            "goal" => goal = Some(parse_goal(value)?),
+            "moves" => move_limit = Some(value.parse().wrap_err("invalid move limit")?),
+            other => bail!("unknown key `{other}`"),
+        }
+    }
+
+    let name = name.ok_or_else(|| eyre!("missing `name`"))?;
+    let start = start.ok_or_else(|| eyre!("missing `board`"))?;
+    for spawn in spawns.iter().flatten() {
+        if let Some((row, col)) = spawn.cell
+            && (row >= start.dim || col >= start.dim)
+        {
+            bail!("spawn at {row},{col} is off the board");
+        }
+    }
+    Ok(Puzzle {
+        id: format!("{pack}/{name}"),
+        name,
+        start,
+        spawns: spawns.unwrap_or_default(),
+        goal: goal.ok_or_else(|| eyre!("missing `goal`"))?,
+        move_limit: move_limit.ok_or_else(|| eyre!("missing `moves`"))?,
+    })
+}
+
+fn parse_spawns(text: &str) -> color_eyre::Result<Vec<Spawn>> {
+    text.split_whitespace()
+        .map(|token| {
+            let (value, cell) = match token.split_once('@') {
+                Some((value, cell)) => {
+                    let (row, col) = cell
+                        .split_once(',')
+                        .ok_or_else(|| eyre!("bad spawn cell `{cell}`"))?;
+                    (value, Some((row.parse()?, col.parse()?)))
+                }
+                None => (token, None),
+            };
+            Ok(Spawn {
+                value: value
+                    .parse()
+                    .wrap_err_with(|| format!("bad spawn `{token}`"))?,
+                cell,
+            })
+        })
+        .collect()
+}
+
+fn parse_goal(text: &str) -> color_eyre::Result<Goal> {
+    let (kind, amount) = text
+        .split_once(' ')
+        .ok_or_else(|| eyre!("bad goal `{text}`"))?;
+    let amount = amount.trim();
+    Ok(match kind {
+        "reach" => Goal::Reach(amount.parse()?),
+        "tiles" => Goal::Tiles(amount.parse()?),
+        "score" => Goal::Score(amount.parse()?),
+        other => bail!("unknown goal `{other}`, expected reach, tiles or score"),
+    })
+}
+
+/// The pack shipped with the game.
+pub fn builtin() -> Vec<Puzzle> {
+    parse_pack("builtin", BUILTIN).expect("the built-in pack is valid")
+}
+
+/// Directory scanned for extra packs, every `.txt` file in it is one pack.
+pub fn packs_dir() -> PathBuf {
+    let mut path = db::data_dir();
+    path.push("puzzles");
+    path
+}
+
+/// Reads the pack in `path`, named after the file.
+pub fn load_pack(path: &Path) -> color_eyre::Result<Vec<Puzzle>> {
+    let text = fs::read_to_string(path).wrap_err_with(|| format!("failed to read {path:?}"))?;
+    let pack = path
+        .file_stem()
+        .map_or("pack".into(), |stem| stem.to_string_lossy());
+    parse_pack(&pack, &text)
+}
+
+/// The built-in pack followed by the packs in [`packs_dir`], sorted by file name. A pack that
+/// fails to load is skipped, the second list says which and why.
+pub fn load_all() -> (Vec<Puzzle>, Vec<String>) {
+    load_dir(&packs_dir())
+}
+
+fn load_dir(dir: &Path) -> (Vec<Puzzle>, Vec<String>) {
+    let mut puzzles = builtin();
+    let mut skipped = Vec::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return (puzzles, skipped);
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .collect();
+    paths.sort();
+    for path in paths {
+        match load_pack(&path) {
+            Ok(pack) => puzzles.extend(pack),
+            Err(error) => skipped.push(format!("{error:#}")),
+        }
+    }
+    (puzzles, skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_bad_pack_is_skipped() {
+        let dir = std::env::temp_dir().join(format!("r2048-packs-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("good.txt"),
+            "name: Pair\nboard: 2,2/0,0\ngoal: reach 4\nmoves: 1\n",
+        )
+        .unwrap();
+        fs::write(dir.join("bad.txt"), "name: Broken\nboard: 2,2/0,0\n").unwrap();
+        fs::write(dir.join("notes.md"), "not a pack").unwrap();
+
+        let (puzzles, skipped) = load_dir(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(puzzles.len(), builtin().len() + 1);
+        assert_eq!(puzzles.last().unwrap().id, "good/Pair");
+        assert_eq!(skipped.len(), 1);
+        assert!(skipped[0].contains("bad"), "{}", skipped[0]);
+        assert!(skipped[0].contains("missing `goal`"), "{}", skipped[0]);
+
+        let (puzzles, skipped) = load_dir(&dir);
+        assert_eq!((puzzles.len(), skipped.len()), (builtin().len(), 0));
+    }
+
+    #[test]
+    fn builtin_pack_parses() {
+        let puzzles = parse_pack("builtin", BUILTIN).unwrap();
+        assert_eq!(puzzles.len(), BUILTIN.matches("name:").count());
+        for puzzle in &puzzles {
+            assert!(puzzle.move_limit > 0, "{}", puzzle.name);
+            assert!(!puzzle.solved(&puzzle.board()), "{}", puzzle.name);
+        }
+        let mut ids: Vec<&str> = puzzles.iter().map(|p| p.id.as_str()).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(ids.len(), puzzles.len());
+    }
+}
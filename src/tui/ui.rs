@@ -1,5 +1,7 @@
-use crate::board::State;
-use crate::events::app::App;
+use crate::board::{Mode, Pressure, Rotation, Special, State, Variant};
+use crate::events::app::{App, SPIN_TICKS};
+use crate::events::event::TICK_FPS;
+use crate::powerup::PowerUp;
 use ratatui::{
     buffer::Buffer,
     layout::{Alignment, Constraint, Layout, Rect},
@@ -19,7 +21,7 @@ impl Widget for &App {
                     .direction(Direction::Vertical)
                     .constraints([
                         Constraint::Percentage(35),
-                        Constraint::Length(10),
+                        Constraint::Length(16),
                         Constraint::Percentage(55),
                     ])
                     .split(area);
@@ -27,9 +29,9 @@ impl Widget for &App {
                 let horizontal = Layout::default()
                     .direction(Direction::Horizontal)
                     .constraints([
-                        Constraint::Percentage(20),
-                        Constraint::Percentage(60),
-                        Constraint::Percentage(20),
+                        Constraint::Percentage(15),
+                        Constraint::Percentage(70),
+                        Constraint::Percentage(15),
                     ])
                     .split(vertical[1]);
 
@@ -58,20 +60,111 @@ impl Widget for &App {
                     .alignment(ratatui::layout::Alignment::Center);
                 title.render(inner_chunks[0], buf);
 
-                let highest_score_paragraph = Paragraph::new(format!(
+                let rules = self.rules.signature(self.dim);
+                let mut highest = format!(
                     "Highest Score: {}",
-                    self.board.db.borrow().get_score().unwrap()
-                ))
-                .style(Style::default().fg(Color::Yellow))
-                .alignment(ratatui::layout::Alignment::Center);
+                    self.db.get_score(&rules, self.rules.difficulty).unwrap()
+                );
+                if let Some(powered) = self
+                    .db
+                    .powered_scores("classic", &rules, self.rules.difficulty, 1)
+                    .unwrap_or_default()
+                    .first()
+                {
+                    highest.push_str(&format!(" · with power-ups: {powered}"));
+                }
+                let highest_score_paragraph = Paragraph::new(highest)
+                    .style(Style::default().fg(Color::Yellow))
+                    .alignment(ratatui::layout::Alignment::Center);
                 highest_score_paragraph.render(inner_chunks[1], buf);
 
+                let best = self
+                    .db
+                    .time_attack_scores(self.time_limit, &rules, self.rules.difficulty, 3)
+                    .unwrap_or_default();
+                let mut best = if best.is_empty() {
+                    "-".to_string()
+                } else {
+                    best.iter()
+                        .map(u32::to_string)
+                        .collect::<Vec<_>>()
+                        .join(" · ")
+                };
+                if let Some(powered) = Mode::TimeAttack(self.time_limit)
+                    .leaderboard()
+                    .and_then(|board| {
+                        self.db
+                            .powered_scores(&board, &rules, self.rules.difficulty, 1)
+                            .ok()
+                    })
+                    .and_then(|scores| scores.first().copied())
+                {
+                    best.push_str(&format!(", powered {powered}"));
+                }
+                let settings = vec![
+                    Line::styled(
+                        format!("Scores for {rules}"),
+                        Style::default().fg(Color::Gray),
+                    ),
+                    Line::from(format!(
+                        "Game: {} [v], target {} [t]",
+                        self.rules.variant.name(),
+                        self.rules.goal()
+                    )),
+                    Line::from(format!(
+                        "Walls: {} [w], wrap-around: {} [r], gravity: {} [f]",
+                        self.rules.walls.name(),
+                        if self.rules.wrap { "on" } else { "off" },
+                        if self.rules.gravity { "on" } else { "off" }
+                    )),
+                    Line::from(format!(
+                        "Special tiles: {} [s], difficulty: {} [g]",
+                        if self.rules.specials.any() {
+                            "on"
+                        } else {
+                            "off"
+                        },
+                        self.rules.difficulty.name()
+                    )),
+                    Line::from(match self.rules.rotation {
+                        Rotation::Off => "Rotation: off [o]".to_string(),
+                        rotation => format!(
+                            "Rotation: {} every {} moves [o]",
+                            rotation.name(),
+                            self.rules.rotate_every
+                        ),
+                    }),
+                    Line::from(match self.rules.pressure {
+                        Pressure::Off => "Pressure: off [p]".to_string(),
+                        pressure => format!(
+                            "Pressure: {} after {} s [p]",
+                            pressure.name(),
+                            self.rules.pressure_seconds
+                        ),
+                    }),
+                    Line::from(format!(
+                        "Time attack: {} min [d], best {best}",
+                        self.time_limit / 60
+                    )),
+                ];
+                Paragraph::new(settings)
+                    .style(Style::default().fg(Color::Rgb(215, 149, 43)))
+                    .alignment(Alignment::Center)
+                    .wrap(ratatui::widgets::Wrap { trim: true })
+                    .render(inner_chunks[2], buf);
+
                 let button_chunks = Layout::default()
                     .direction(Direction::Horizontal)
-                    .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                    .constraints([
+                        Constraint::Ratio(1, 5),
+                        Constraint::Ratio(1, 5),
+                        Constraint::Ratio(1, 5),
+                        Constraint::Ratio(1, 5),
+                        Constraint::Ratio(1, 5),
+                    ])
                     .split(inner_chunks[3]);
 
-                let labels = ["Start", "Quit"];
+                let labels = ["Start", "Time Attack", "Puzzles", "Editor", "Quit"];
                 for (i, label) in labels.iter().enumerate() {
                     let style = if self.selected_button == i {
                         Style::default()
@@ -92,70 +185,383 @@ impl Widget for &App {
             }
 
             State::Playing => {
+                let (area, meter_area) = if self.meter.is_some() {
+                    let rows = Layout::default()
+                        .direction(Direction::Vertical)
+                        .constraints([Constraint::Min(0), Constraint::Length(3)])
+                        .split(area);
+                    (rows[0], Some(rows[1]))
+                } else {
+                    (area, None)
+                };
+
+                let area = match self.mode {
+                    Mode::Puzzle(_) => area,
+                    Mode::Classic | Mode::TimeAttack(_) | Mode::Sandbox => {
+                        let rows = Layout::default()
+                            .direction(Direction::Vertical)
+                            .constraints([Constraint::Min(0), Constraint::Length(1)])
+                            .split(area);
+                        let mut held = vec![Span::raw("power-ups: ")];
+                        for (i, &power) in PowerUp::ALL.iter().enumerate() {
+                            let count = self.inventory.count(power);
+                            let color = if count > 0 {
+                                Color::Magenta
+                            } else {
+                                Color::DarkGray
+                            };
+                            held.push(Span::styled(
+                                format!(" [{}] {} ×{count} ", i + 1, power.name()),
+                                Style::default().fg(color),
+                            ));
+                        }
+                        Paragraph::new(Line::from(held))
+                            .alignment(Alignment::Center)
+                            .render(rows[1], buf);
+                        rows[0]
+                    }
+                };
+
+                let area = match self.mode {
+                    Mode::Classic | Mode::Sandbox => area,
+                    Mode::TimeAttack(seconds) => {
+                        let rows = Layout::default()
+                            .direction(Direction::Vertical)
+                            .constraints([Constraint::Length(3), Constraint::Min(0)])
+                            .split(area);
+                        let left = self.seconds_left();
+                        let color = if left <= 10 { Color::Red } else { Color::Cyan };
+                        Gauge::default()
+                            .block(Block::default().title("time left").borders(Borders::ALL))
+                            .gauge_style(Style::default().fg(color).add_modifier(Modifier::BOLD))
+                            .ratio((f64::from(left) / f64::from(seconds)).min(1.0))
+                            .label(format!("{}:{:02}", left / 60, left % 60))
+                            .render(rows[0], buf);
+                        rows[1]
+                    }
+                    Mode::Puzzle(index) => {
+                        let rows = Layout::default()
+                            .direction(Direction::Vertical)
+                            .constraints([Constraint::Length(3), Constraint::Min(0)])
+                            .split(area);
+                        let puzzle = &self.puzzles[index];
+                        let left = puzzle.move_limit.saturating_sub(self.history.len() as u32);
+                        Paragraph::new(Line::from(vec![
+                            Span::styled(
+                                format!("Goal: {}", puzzle.goal.describe()),
+                                Style::default().fg(Color::Yellow),
+                            ),
+                            Span::raw("  |  "),
+                            Span::styled(
+                                format!("moves left: {left}/{}", puzzle.move_limit),
+                                Style::default().fg(if left <= 1 {
+                                    Color::Red
+                                } else {
+                                    Color::Cyan
+                                }),
+                            ),
+                        ]))
+                        .block(
+                            Block::default()
+                                .title(format!("puzzle: {}", puzzle.name))
+                                .borders(Borders::ALL),
+                        )
+                        .alignment(Alignment::Center)
+                        .render(rows[0], buf);
+                        rows[1]
+                    }
+                };
+
+                let area = match self.moves_to_rotation() {
+                    None => area,
+                    Some(left) => {
+                        let rows = Layout::default()
+                            .direction(Direction::Vertical)
+                            .constraints([Constraint::Length(1), Constraint::Min(0)])
+                            .split(area);
+                        let every = self.board.rules.rotate_every;
+                        let (arrow, turn) = match self.board.rules.rotation {
+                            Rotation::Clockwise => ("↻", "clockwise"),
+                            Rotation::Counterclockwise => ("↺", "counterclockwise"),
+                            _ => ("⟳", "either way"),
+                        };
+                        let color = match left {
+                            1 => Color::Red,
+                            2 | 3 => Color::Yellow,
+                            _ => Color::Cyan,
+                        };
+                        let moves = if left == 1 { "move" } else { "moves" };
+                        // One block per move of the countdown, when they fit on the row.
+                        let blocks = if every <= 20 {
+                            format!(
+                                " {}{}",
+                                "■".repeat(left as usize),
+                                "□".repeat((every - left) as usize)
+                            )
+                        } else {
+                            String::new()
+                        };
+                        Paragraph::new(Line::styled(
+                            format!("{arrow} turns {turn} in {left} {moves}{blocks}"),
+                            Style::default().fg(color),
+                        ))
+                        .alignment(Alignment::Center)
+                        .render(rows[0], buf);
+                        rows[1]
+                    }
+                };
+
+                let area = match self.board.rules.pressure {
+                    Pressure::Off => area,
+                    pressure => {
+                        let rows = Layout::default()
+                            .direction(Direction::Vertical)
+                            .constraints([Constraint::Length(1), Constraint::Min(0)])
+                            .split(area);
+                        let full = self.pressure_ticks().max(1);
+                        let ratio = (f64::from(self.pressure) / f64::from(full)).min(1.0);
+                        let color = match ratio {
+                            r if r <= 0.25 => Color::Red,
+                            r if r <= 0.5 => Color::Yellow,
+                            _ => Color::Green,
+                        };
+                        let then = match pressure {
+                            Pressure::Move => "a move is played",
+                            _ => "a tile spawns",
+                        };
+                        Gauge::default()
+                            .gauge_style(Style::default().fg(color).bg(Color::DarkGray))
+                            .ratio(ratio)
+                            .label(format!(
+                                "{:.1}s, then {then}",
+                                f64::from(self.pressure) / TICK_FPS
+                            ))
+                            .render(rows[0], buf);
+                        rows[1]
+                    }
+                };
+
+                // What the bot reports about its last move, to tune it by, such as the node
+                // counts and timings of the `deep` search.
+                let area = match self.strategy.report() {
+                    Some(report) if self.autoplay || self.hint.is_some() => {
+                        let rows = Layout::default()
+                            .direction(Direction::Vertical)
+                            .constraints([Constraint::Length(2), Constraint::Min(0)])
+                            .split(area);
+                        Paragraph::new(Line::styled(
+                            format!("{}: {report}", self.strategy.name()),
+                            Style::default().fg(Color::DarkGray),
+                        ))
+                        .wrap(Wrap { trim: true })
+                        .render(rows[0], buf);
+                        rows[1]
+                    }
+                    _ => area,
+                };
+
+                let hint = match self.hint {
+                    _ if self.strategy.is_thinking() => "…",
+                    Some(direction) => direction.arrow(),
+                    None => "-",
+                };
+                let on_off = |flag: bool| if flag { "on" } else { "off" };
+                let status = match self.targeting {
+                    Some(targeting) => Line::styled(
+                        format!(
+                            " {}: pick {} with [enter] | [esc] cancel ",
+                            targeting.power.name(),
+                            match targeting.picked {
+                                None if targeting.power.picks() == 2 => "the first tile",
+                                Some(_) => "the second tile",
+                                None => "a tile",
+                            }
+                        ),
+                        Style::default().fg(Color::Yellow),
+                    ),
+                    None => Line::from(format!(
+                        " [s] bot: {} | [h] hint: {} | [a] autoplay: {} | [c] coach: {} ",
+                        self.strategy.name(),
+                        hint,
+                        on_off(self.autoplay),
+                        on_off(self.coaching)
+                    )),
+                };
+                let reached = self.milestones_reached();
+                let milestones = match self.mode {
+                    Mode::Puzzle(_) | Mode::Sandbox => &[][..],
+                    Mode::Classic | Mode::TimeAttack(_) => &self.milestones[..],
+                };
+                let mut progress = Vec::new();
+                for (i, milestone) in milestones.iter().enumerate() {
+                    if i == 0 {
+                        progress.push(Span::raw(" "));
+                    }
+                    let (mark, color) = match i.cmp(&reached) {
+                        std::cmp::Ordering::Less => ("✓", Color::Green),
+                        std::cmp::Ordering::Equal => ("…", Color::Yellow),
+                        std::cmp::Ordering::Greater => ("·", Color::DarkGray),
+                    };
+                    progress.push(Span::styled(
+                        format!("{milestone}{mark} "),
+                        Style::default().fg(color),
+                    ));
+                }
+
+                let toast = self.toast.as_ref().map_or(Line::default(), |(text, _)| {
+                    Line::styled(format!(" {text} "), Style::default().fg(Color::Yellow))
+                });
+
                 let game_block = Block::default()
+                    .title(Line::from(progress).left_aligned())
                     .title("r2048")
+                    .title(toast.right_aligned())
+                    .title_bottom(status.centered())
                     .borders(Borders::ALL)
                     .style(Style::default().bg(Color::Black))
                     .title_alignment(Alignment::Center);
                 let inner_area = game_block.inner(area);
                 game_block.render(area, buf);
 
-                let cell_width = inner_area.width / 4;
-                let cell_height = inner_area.height / 4;
+                let dim = self.board.dim as u16;
+                let cell_width = inner_area.width / dim;
+                let cell_height = inner_area.height / dim;
 
-                let grid_width = cell_width * 4;
-                let grid_height = cell_height * 4;
+                let grid_width = cell_width * dim;
+                let grid_height = cell_height * dim;
                 let grid_start_x = inner_area.x + (inner_area.width - grid_width) / 2;
                 let grid_start_y = inner_area.y + (inner_area.height - grid_height) / 2;
 
-                for row in 0..4 {
-                    for col in 0..4 {
+                let variant = self.board.rules.variant;
+                match &self.spin {
+                    Some((before, clockwise, ticks)) => {
+                        // Every tile swings round the centre of the grid, a little more each tick.
+                        let turn =
+                            std::f64::consts::FRAC_PI_2 * f64::from(*ticks) / f64::from(SPIN_TICKS);
+                        let (sin, cos) = if *clockwise { turn } else { -turn }.sin_cos();
+                        let half = f64::from(dim) / 2.0;
+                        for (row, col) in before.positions() {
+                            let wall = before.walls[row][col];
+                            let value = before.size[row][col];
+                            if !wall && value == 0 {
+                                continue;
+                            }
+                            let x = col as f64 + 0.5 - half;
+                            let y = row as f64 + 0.5 - half;
+                            let (x, y) = (x * cos - y * sin, x * sin + y * cos);
+                            let left =
+                                f64::from(grid_start_x) + (x + half - 0.5) * f64::from(cell_width);
+                            let top =
+                                f64::from(grid_start_y) + (y + half - 0.5) * f64::from(cell_height);
+                            let cell_area = Rect {
+                                x: left.round().max(0.0) as u16,
+                                y: top.round().max(0.0) as u16,
+                                width: cell_width,
+                                height: cell_height,
+                            }
+                            .intersection(inner_area);
+                            let special = before.special[row][col];
+                            render_cell(cell_area, buf, wall, value, special, variant);
+                        }
+                    }
+                    None => {
+                        let (shown, shown_special) = self.shown_cells();
+                        for row in 0..dim as usize {
+                            for col in 0..dim as usize {
+                                let cell_area = Rect {
+                                    x: grid_start_x + col as u16 * cell_width,
+                                    y: grid_start_y + row as u16 * cell_height,
+                                    width: cell_width,
+                                    height: cell_height,
+                                };
+                                render_cell(
+                                    cell_area,
+                                    buf,
+                                    self.board.walls[row][col],
+                                    shown[row][col],
+                                    shown_special[row][col],
+                                    variant,
+                                );
+                            }
+                        }
+                    }
+                }
+
+                if let Some(targeting) = self.targeting {
+                    let frames = [
+                        (targeting.picked, Color::Magenta),
+                        (Some(targeting.cursor), Color::Yellow),
+                    ];
+                    for ((row, col), color) in frames
+                        .into_iter()
+                        .filter_map(|(cell, color)| cell.map(|c| (c, color)))
+                    {
                         let cell_area = Rect {
-                            x: grid_start_x + col * cell_width,
-                            y: grid_start_y + row * cell_height,
+                            x: grid_start_x + col as u16 * cell_width,
+                            y: grid_start_y + row as u16 * cell_height,
                             width: cell_width,
                             height: cell_height,
                         };
-
-                        let value = self.board.size[row as usize][col as usize];
-                        let bg_color = if value != 0 {
-                            crate::decoration::get_background_color(value)
-                        } else {
-                            Color::Black
-                        };
-
                         Block::default()
                             .borders(Borders::ALL)
-                            .style(Style::default().bg(bg_color))
+                            .border_type(BorderType::Thick)
+                            .border_style(Style::default().fg(color).add_modifier(Modifier::BOLD))
                             .render(cell_area, buf);
+                    }
+                }
 
-                        if value != 0 {
-                            let pixel_size = if cell_area.width < 8 || cell_area.height < 4 {
-                                PixelSize::Quadrant
-                            } else {
-                                PixelSize::Full
-                            };
+                // Tiles leaving one edge come back on the other, marked on the outer borders.
+                if self.board.rules.wrap && cell_width > 2 && cell_height > 2 {
+                    let marker = Style::default()
+                        .fg(Color::Cyan)
+                        .add_modifier(Modifier::BOLD);
+                    let (left, right) = (grid_start_x, grid_start_x + grid_width - 1);
+                    let (top, bottom) = (grid_start_y, grid_start_y + grid_height - 1);
+                    for i in 0..dim {
+                        let y = grid_start_y + i * cell_height + cell_height / 2;
+                        buf[(left, y)].set_symbol("↔").set_style(marker);
+                        buf[(right, y)].set_symbol("↔").set_style(marker);
+                        let x = grid_start_x + i * cell_width + cell_width / 2;
+                        buf[(x, top)].set_symbol("↕").set_style(marker);
+                        buf[(x, bottom)].set_symbol("↕").set_style(marker);
+                    }
+                }
 
-                            let big_text = BigText::builder()
-                                .centered()
-                                .pixel_size(pixel_size)
-                                .style(match value {
-                                    2 | 4 => Style::new().blue(),
-                                    _ => Style::new().black().bold(),
-                                })
-                                .lines(vec![Line::from(value.to_string())])
-                                .build();
-
-                            let text_area = Rect {
-                                x: cell_area.x + 1,
-                                y: cell_area.y + 1,
-                                width: cell_area.width.saturating_sub(2),
-                                height: cell_area.height.saturating_sub(2),
-                            };
+                if let Some(meter_area) = meter_area {
+                    let halves = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                        .split(meter_area);
 
-                            big_text.render(text_area, buf);
-                        }
-                    }
+                    let (ratio, label, expected) = match self.estimate {
+                        Some(e) => (
+                            e.win_probability,
+                            format!("{:.0}%", e.win_probability * 100.0),
+                            format!("{:.0} ({} rollouts)", e.expected_score, e.rollouts),
+                        ),
+                        None => (0.0, "...".to_string(), "...".to_string()),
+                    };
+
+                    Gauge::default()
+                        .block(
+                            Block::default()
+                                .title(format!("[m] chance of {}", self.board.rules.goal()))
+                                .borders(Borders::ALL),
+                        )
+                        .gauge_style(Style::default().fg(Color::Rgb(215, 149, 43)))
+                        .ratio(ratio)
+                        .label(label)
+                        .render(halves[0], buf);
+
+                    Paragraph::new(expected)
+                        .block(
+                            Block::default()
+                                .title("expected final score")
+                                .borders(Borders::ALL),
+                        )
+                        .style(Style::default().fg(Color::LightMagenta))
+                        .alignment(Alignment::Center)
+                        .render(halves[1], buf);
                 }
             }
 
@@ -182,9 +588,17 @@ impl Widget for &App {
 
                 let popup_area = horizontal_chunks[1];
 
+                let coach_summary = self.coach_summary();
+                let powerup_summary = self.powerup_summary();
+                let autoplay_summary = self.autoplay_summary();
+                let popup_height = 4
+                    + u16::from(coach_summary.is_some())
+                    + u16::from(powerup_summary.is_some())
+                    + u16::from(autoplay_summary.is_some());
+
                 let popup_chunks = Layout::default()
                     .direction(Direction::Vertical)
-                    .constraints([Constraint::Length(4), Constraint::Length(3)])
+                    .constraints([Constraint::Length(popup_height), Constraint::Length(3)])
                     .split(popup_area);
 
                 let block = Block::default()
@@ -195,15 +609,17 @@ impl Widget for &App {
                     .title_alignment(Alignment::Center);
 
                 let score_value = self.board.calculate_score();
-                self.board
-                    .db
-                    .borrow_mut()
-                    .update_score(score_value)
-                    .unwrap();
 
-                let lines = vec![
+                let mut lines = vec![
                     Line::styled(
-                        "You got 2048 on the board",
+                        match self.puzzle() {
+                            Some(puzzle) => format!(
+                                "Solved \"{}\" in {} moves",
+                                puzzle.name,
+                                self.history.len()
+                            ),
+                            None => format!("You got {} on the board", self.board.rules.goal()),
+                        },
                         Style::default()
                             .fg(Color::White)
                             .add_modifier(Modifier::BOLD),
@@ -218,6 +634,15 @@ impl Widget for &App {
                         ),
                     ]),
                 ];
+                if let Some(summary) = coach_summary {
+                    lines.push(Line::styled(summary, Style::default().fg(Color::Yellow)));
+                }
+                if let Some(summary) = powerup_summary {
+                    lines.push(Line::styled(summary, Style::default().fg(Color::Magenta)));
+                }
+                if let Some(summary) = autoplay_summary {
+                    lines.push(Line::styled(summary, Style::default().fg(Color::DarkGray)));
+                }
 
                 let paragraph = Paragraph::new(lines)
                     .block(block)
@@ -229,10 +654,19 @@ impl Widget for &App {
 
                 let buttons = Layout::default()
                     .direction(Direction::Horizontal)
-                    .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                    .constraints([
+                        Constraint::Ratio(1, 3),
+                        Constraint::Ratio(1, 3),
+                        Constraint::Ratio(1, 3),
+                    ])
                     .split(popup_chunks[1]);
 
-                let labels = ["One more time?", "Main Menu"];
+                let back = if self.mode == Mode::Sandbox {
+                    "Editor"
+                } else {
+                    "Main Menu"
+                };
+                let labels = ["One more time?", "Analyze", back];
 
                 for (i, label) in labels.iter().enumerate() {
                     let style = if self.selected_button == i {
@@ -275,30 +709,48 @@ impl Widget for &App {
 
                 let popup_area = horizontal_chunks[1];
 
+                let coach_summary = self.coach_summary();
+                let time_up = match self.mode {
+                    Mode::TimeAttack(seconds) => Some(seconds),
+                    Mode::Classic | Mode::Puzzle(_) | Mode::Sandbox => None,
+                };
+                let powerup_summary = self.powerup_summary();
+                let autoplay_summary = self.autoplay_summary();
+                let popup_height = 4
+                    + u16::from(coach_summary.is_some())
+                    + u16::from(time_up.is_some())
+                    + u16::from(powerup_summary.is_some())
+                    + u16::from(autoplay_summary.is_some());
+
                 let popup_chunks = Layout::default()
                     .direction(Direction::Vertical)
-                    .constraints([Constraint::Length(4), Constraint::Length(3)])
+                    .constraints([Constraint::Length(popup_height), Constraint::Length(3)])
                     .split(popup_area);
 
                 let block = Block::default()
-                    .title("Game Over")
+                    .title(if time_up.is_some() && self.clock == 0 {
+                        "Time's up!"
+                    } else {
+                        "Game Over"
+                    })
                     .borders(Borders::ALL)
                     .style(Style::default().fg(Color::Red).bg(Color::Black))
                     .border_type(ratatui::widgets::BorderType::Double)
                     .title_alignment(Alignment::Center);
 
                 let score_value = self.board.calculate_score();
-                self.board
-                    .db
-                    .borrow_mut()
-                    .update_score(score_value)
-                    .unwrap();
 
-                let lines = vec![
-                    Line::styled(
-                        "No more possible moves, you were so close!",
-                        Style::default().fg(Color::White),
-                    ),
+                let message = match (time_up, self.puzzle()) {
+                    (Some(seconds), _) if self.clock == 0 => {
+                        format!("Your {}-minute run is over", seconds / 60)
+                    }
+                    (_, Some(puzzle)) if self.history.len() as u32 >= puzzle.move_limit => {
+                        format!("Out of moves, the goal was to {}", puzzle.goal.describe())
+                    }
+                    _ => "No more possible moves, you were so close!".to_string(),
+                };
+                let mut lines = vec![
+                    Line::styled(message, Style::default().fg(Color::White)),
                     Line::from(vec![
                         Span::styled("SCORE : ", Style::default().fg(Color::White)),
                         Span::styled(
@@ -309,6 +761,39 @@ impl Widget for &App {
                         ),
                     ]),
                 ];
+                if let Some(seconds) = time_up {
+                    let powered = !self.powerups.is_empty();
+                    let difficulty = self.board.rules.difficulty;
+                    let rules = self.board.rules.signature(self.board.dim);
+                    let best = match self.mode.leaderboard() {
+                        Some(board) if powered => {
+                            self.db.powered_scores(&board, &rules, difficulty, 3)
+                        }
+                        _ => self.db.time_attack_scores(seconds, &rules, difficulty, 3),
+                    }
+                    .unwrap_or_default()
+                    .iter()
+                    .map(u32::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" · ");
+                    lines.push(Line::styled(
+                        format!(
+                            "Best {}{}-minute runs: {best}",
+                            if powered { "powered " } else { "" },
+                            seconds / 60
+                        ),
+                        Style::default().fg(Color::Cyan),
+                    ));
+                }
+                if let Some(summary) = coach_summary {
+                    lines.push(Line::styled(summary, Style::default().fg(Color::Yellow)));
+                }
+                if let Some(summary) = powerup_summary {
+                    lines.push(Line::styled(summary, Style::default().fg(Color::Magenta)));
+                }
+                if let Some(summary) = autoplay_summary {
+                    lines.push(Line::styled(summary, Style::default().fg(Color::DarkGray)));
+                }
 
                 let paragraph = Paragraph::new(lines)
                     .block(block)
@@ -320,10 +805,19 @@ impl Widget for &App {
 
                 let buttons = Layout::default()
                     .direction(Direction::Horizontal)
-                    .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                    .constraints([
+                        Constraint::Ratio(1, 3),
+                        Constraint::Ratio(1, 3),
+                        Constraint::Ratio(1, 3),
+                    ])
                     .split(popup_chunks[1]);
 
-                let labels = ["Maybe try again?", "Main Menu"];
+                let back = if self.mode == Mode::Sandbox {
+                    "Editor"
+                } else {
+                    "Main Menu"
+                };
+                let labels = ["Maybe try again?", "Analyze", back];
 
                 for (i, label) in labels.iter().enumerate() {
                     let style = if self.selected_button == i {
@@ -343,6 +837,481 @@ impl Widget for &App {
                     button.render(buttons[i], buf);
                 }
             }
+            State::Analysis => {
+                Clear.render(area, buf);
+
+                let Some(reviews) = &self.analysis else {
+                    Paragraph::new(format!("Analyzing {} moves...", self.history.len()))
+                        .style(Style::default().fg(Color::Yellow))
+                        .alignment(Alignment::Center)
+                        .block(Block::default().borders(Borders::ALL).title("Analysis"))
+                        .render(area, buf);
+                    return;
+                };
+
+                let columns = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Percentage(65), Constraint::Percentage(35)])
+                    .split(area);
+
+                // Moves that risk losing are valued near `LOST`, clip them so they don't flatten
+                // the rest of the graph.
+                let high = reviews.iter().map(|r| r.best_value).fold(1.0f64, f64::max);
+                let low = reviews
+                    .iter()
+                    .map(|r| r.played_value)
+                    .fold(0.0f64, f64::min)
+                    .max(-high);
+
+                let best: Vec<(f64, f64)> = reviews
+                    .iter()
+                    .enumerate()
+                    .map(|(i, r)| (i as f64, r.best_value.max(low)))
+                    .collect();
+                let played: Vec<(f64, f64)> = reviews
+                    .iter()
+                    .enumerate()
+                    .map(|(i, r)| (i as f64, r.played_value.max(low)))
+                    .collect();
+                let blunder_points: Vec<(f64, f64)> = reviews
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, r)| r.is_blunder())
+                    .map(|(i, r)| (i as f64, r.played_value.max(low)))
+                    .collect();
+                let moves = reviews.len().max(1) as f64;
+
+                let chart = Chart::new(vec![
+                    Dataset::default()
+                        .name("best")
+                        .marker(symbols::Marker::Braille)
+                        .graph_type(GraphType::Line)
+                        .style(Style::default().fg(Color::Green))
+                        .data(&best),
+                    Dataset::default()
+                        .name("played")
+                        .marker(symbols::Marker::Braille)
+                        .graph_type(GraphType::Line)
+                        .style(Style::default().fg(Color::Cyan))
+                        .data(&played),
+                    Dataset::default()
+                        .name("blunder")
+                        .marker(symbols::Marker::Dot)
+                        .graph_type(GraphType::Scatter)
+                        .style(Style::default().fg(Color::Red))
+                        .data(&blunder_points),
+                ])
+                .block(
+                    Block::default()
+                        .title("Evaluation per move")
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded),
+                )
+                .x_axis(
+                    Axis::default()
+                        .title("move")
+                        .bounds([0.0, moves])
+                        .labels(["0".to_string(), format!("{}", reviews.len())]),
+                )
+                .y_axis(
+                    Axis::default()
+                        .bounds([low, high])
+                        .labels([format!("{low:.0}"), format!("{high:.0}")]),
+                );
+                chart.render(columns[0], buf);
+
+                let side = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Min(3), Constraint::Length(6)])
+                    .split(columns[1]);
+
+                let blunders = self.blunders();
+                let visible = side[0].height.saturating_sub(2) as usize;
+                let offset = self
+                    .selected_review
+                    .saturating_sub(visible.saturating_sub(1));
+                let items: Vec<Line> = reviews
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, r)| r.is_blunder())
+                    .enumerate()
+                    .skip(offset)
+                    .take(visible)
+                    .map(|(n, (i, r))| {
+                        let text = format!(
+                            "#{:<4} played {} best {}  -{:.0}%",
+                            i + 1,
+                            r.played.arrow(),
+                            r.best.arrow(),
+                            r.loss() * 100.0
+                        );
+                        if n == self.selected_review {
+                            Line::styled(text, Style::default().bg(Color::Red).fg(Color::Black))
+                        } else {
+                            Line::from(text)
+                        }
+                    })
+                    .collect();
+
+                Paragraph::new(items)
+                    .block(
+                        Block::default()
+                            .title(format!("Blunders ({})", blunders.len()))
+                            .title_bottom(Line::from(" [↑/↓] select  [q] menu ").centered())
+                            .borders(Borders::ALL)
+                            .border_type(BorderType::Rounded),
+                    )
+                    .render(side[0], buf);
+
+                let position: Vec<Line> = blunders
+                    .get(self.selected_review)
+                    .map(|r| {
+                        let dim = r.board.dim;
+                        r.board.size[..dim]
+                            .iter()
+                            .map(|row| {
+                                Line::from(
+                                    row[..dim]
+                                        .iter()
+                                        .map(|&v| format!("{v:>6}"))
+                                        .collect::<String>(),
+                                )
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+
+                Paragraph::new(position)
+                    .block(Block::default().title("Position").borders(Borders::ALL))
+                    .alignment(Alignment::Center)
+                    .render(side[1], buf);
+            }
+
+            State::Puzzles => {
+                let columns = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+                    .split(area);
+
+                let visible = columns[0].height.saturating_sub(2) as usize;
+                let offset = self
+                    .selected_puzzle
+                    .saturating_sub(visible.saturating_sub(1));
+                let solved = self
+                    .puzzles
+                    .iter()
+                    .filter(|p| matches!(self.db.puzzle_best(&p.id), Ok(Some(_))))
+                    .count();
+                let items: Vec<Line> = self
+                    .puzzles
+                    .iter()
+                    .enumerate()
+                    .skip(offset)
+                    .take(visible)
+                    .map(|(i, p)| {
+                        let best = self.db.puzzle_best(&p.id).ok().flatten();
+                        let mark = if best.is_some() { "✓" } else { "·" };
+                        let text = format!("{mark} {:<24} {}", p.id, p.goal.describe());
+                        if i == self.selected_puzzle {
+                            Line::styled(text, Style::default().bg(Color::Green).fg(Color::Black))
+                        } else if best.is_some() {
+                            Line::styled(text, Style::default().fg(Color::Green))
+                        } else {
+                            Line::from(text)
+                        }
+                    })
+                    .collect();
+
+                Paragraph::new(items)
+                    .block(
+                        Block::default()
+                            .title(format!("Puzzles ({solved}/{} solved)", self.puzzles.len()))
+                            .title_bottom(
+                                Line::from(" [↑/↓] select  [e] play  [q] menu ").centered(),
+                            )
+                            .borders(Borders::ALL)
+                            .border_type(BorderType::Rounded),
+                    )
+                    .render(columns[0], buf);
+
+                let Some(puzzle) = self.puzzles.get(self.selected_puzzle) else {
+                    return;
+                };
+                let best = self.db.puzzle_best(&puzzle.id).ok().flatten();
+                let start = puzzle.board();
+                let mut details: Vec<Line> = start.size[..start.dim]
+                    .iter()
+                    .map(|row| {
+                        Line::from(
+                            row[..start.dim]
+                                .iter()
+                                .map(|&v| format!("{v:>6}"))
+                                .collect::<String>(),
+                        )
+                    })
+                    .collect();
+                details.push(Line::default());
+                details.push(Line::styled(
+                    format!("Goal: {}", puzzle.goal.describe()),
+                    Style::default().fg(Color::Yellow),
+                ));
+                details.push(Line::from(format!("Move limit: {}", puzzle.move_limit)));
+                details.push(Line::styled(
+                    match best {
+                        Some(moves) => format!("Solved, best {moves} moves"),
+                        None => "Unsolved".to_string(),
+                    },
+                    Style::default().fg(if best.is_some() {
+                        Color::Green
+                    } else {
+                        Color::DarkGray
+                    }),
+                ));
+                for skipped in &self.skipped_packs {
+                    details.push(Line::default());
+                    details.push(Line::styled(
+                        format!("skipped {skipped}"),
+                        Style::default().fg(Color::Red),
+                    ));
+                }
+
+                Paragraph::new(details)
+                    .block(
+                        Block::default()
+                            .title(puzzle.name.as_str())
+                            .borders(Borders::ALL),
+                    )
+                    .alignment(Alignment::Center)
+                    .wrap(Wrap { trim: true })
+                    .render(columns[1], buf);
+            }
+
+            State::Editor => {
+                let editor = &self.editor;
+                let columns = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+                    .split(area);
+
+                let board = &editor.board;
+                let mut grid = vec![Line::default()];
+                for row in 0..board.dim {
+                    let cells: Vec<Span> = (0..board.dim)
+                        .map(|col| {
+                            let value = board.size[row][col];
+                            let wall = board.walls[row][col];
+                            if (row, col) == editor.cursor {
+                                let text = if !editor.input.is_empty() {
+                                    format!("{}_", editor.input)
+                                } else if wall {
+                                    "▓".to_string()
+                                } else if value == 0 {
+                                    "·".to_string()
+                                } else {
+                                    value.to_string()
+                                };
+                                Span::styled(
+                                    format!("{text:>7}"),
+                                    Style::default()
+                                        .bg(Color::Green)
+                                        .fg(Color::Black)
+                                        .add_modifier(Modifier::BOLD),
+                                )
+                            } else if wall {
+                                Span::styled(
+                                    format!("{:>7}", "▓"),
+                                    Style::default().fg(Color::Gray),
+                                )
+                            } else if value == 0 {
+                                Span::styled(
+                                    format!("{:>7}", "·"),
+                                    Style::default().fg(Color::DarkGray),
+                                )
+                            } else {
+                                Span::raw(format!("{value:>7}"))
+                            }
+                        })
+                        .collect();
+                    grid.push(Line::from(cells));
+                    grid.push(Line::default());
+                }
+                if let Some(message) = &editor.message {
+                    grid.push(Line::styled(
+                        message.as_str(),
+                        Style::default().fg(Color::Yellow),
+                    ));
+                }
+
+                Paragraph::new(grid)
+                    .block(
+                        Block::default()
+                            .title("Editor")
+                            .title_bottom(
+                                Line::from(" [p] play  [v] evaluate  [q] menu ").centered(),
+                            )
+                            .borders(Borders::ALL)
+                            .border_type(BorderType::Rounded),
+                    )
+                    .alignment(Alignment::Center)
+                    .render(columns[0], buf);
+
+                let mut side = vec![
+                    Line::from(format!("To move: {}  [t]", editor.to_move.name())),
+                    Line::from(format!(
+                        "Spawns: {}  [r]",
+                        board.rules.spawns.describe(board.rules.variant)
+                    )),
+                ];
+                if let Some((bot, values)) = &editor.evaluations {
+                    side.push(Line::default());
+                    side.push(Line::styled(
+                        format!("{bot} says:"),
+                        Style::default().fg(Color::Cyan),
+                    ));
+                    for d in crate::board::Direction::ALL {
+                        side.push(Line::from(match values[d.index()] {
+                            Some(value) => format!("{} {value:.0}", d.arrow()),
+                            None => format!("{} illegal", d.arrow()),
+                        }));
+                    }
+                }
+                if self.strategy.is_rating() {
+                    side.push(Line::default());
+                    side.push(Line::styled(
+                        format!("{} is thinking...", self.strategy.name()),
+                        Style::default().fg(Color::DarkGray),
+                    ));
+                }
+
+                side.push(Line::default());
+                side.push(Line::styled(
+                    "Library",
+                    Style::default().add_modifier(Modifier::BOLD),
+                ));
+                if editor.library.is_empty() {
+                    side.push(Line::styled(
+                        "no saved positions",
+                        Style::default().fg(Color::DarkGray),
+                    ));
+                }
+                for (i, name) in editor.library.iter().enumerate() {
+                    if i == editor.selected {
+                        side.push(Line::styled(
+                            name.as_str(),
+                            Style::default().bg(Color::Green).fg(Color::Black),
+                        ));
+                    } else {
+                        side.push(Line::from(name.as_str()));
+                    }
+                }
+                if let Some(name) = &editor.naming {
+                    side.push(Line::default());
+                    side.push(Line::styled(
+                        format!("Save as: {name}_"),
+                        Style::default().fg(Color::Yellow),
+                    ));
+                }
+
+                side.push(Line::default());
+                for help in [
+                    "arrows move, digits type a tile",
+                    "[+/-] double/halve  [x] clear cell",
+                    "[w] wall  [c] clear board",
+                    "[s] save  [[/]] select  [o] load",
+                ] {
+                    side.push(Line::styled(help, Style::default().fg(Color::DarkGray)));
+                }
+
+                Paragraph::new(side)
+                    .block(Block::default().title("Position").borders(Borders::ALL))
+                    .render(columns[1], buf);
+            }
         }
     }
 }
+
+/// Draws one cell of the playing grid: a wall, an empty cell or a tile of `variant`.
+fn render_cell(
+    cell_area: Rect,
+    buf: &mut Buffer,
+    wall: bool,
+    value: u32,
+    special: Option<Special>,
+    variant: Variant,
+) {
+    if wall {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Thick)
+            .style(Style::default().fg(Color::Gray).bg(Color::DarkGray));
+        let inner = block.inner(cell_area);
+        block.render(cell_area, buf);
+        let rubble = "░".repeat(inner.width as usize);
+        Paragraph::new(vec![Line::from(rubble); inner.height as usize]).render(inner, buf);
+        return;
+    }
+
+    let bg_color = match special {
+        Some(Special::Wildcard) => Color::Rgb(186, 104, 200),
+        Some(Special::Stone) => Color::Rgb(120, 110, 100),
+        _ if value != 0 => crate::decoration::tile_color(variant, value),
+        _ => Color::Black,
+    };
+
+    let mut block = Block::default()
+        .borders(Borders::ALL)
+        .style(Style::default().bg(bg_color));
+    // Bombs and doublers keep their tile's colour and get a labelled frame.
+    match special {
+        Some(Special::Bomb) => {
+            block = block
+                .border_type(BorderType::Double)
+                .border_style(Style::default().fg(Color::Red))
+                .title(" bomb ");
+        }
+        Some(Special::Doubler) => {
+            block = block
+                .border_type(BorderType::Double)
+                .border_style(Style::default().fg(Color::Green))
+                .title(" x2 ");
+        }
+        _ => {}
+    }
+    let inner = block.inner(cell_area);
+    block.render(cell_area, buf);
+
+    if special == Some(Special::Stone) {
+        let grain = "▒".repeat(inner.width as usize);
+        Paragraph::new(vec![Line::from(grain); inner.height as usize])
+            .style(Style::default().fg(Color::Rgb(90, 82, 74)))
+            .render(inner, buf);
+        return;
+    }
+
+    if value != 0 {
+        let pixel_size = if cell_area.width < 8 || cell_area.height < 4 {
+            PixelSize::Quadrant
+        } else {
+            PixelSize::Full
+        };
+
+        let big_text = BigText::builder()
+            .centered()
+            .pixel_size(pixel_size)
+            .style(crate::decoration::tile_style(variant, value))
+            .lines(vec![Line::from(match special {
+                Some(Special::Wildcard) => "*".to_string(),
+                _ => value.to_string(),
+            })])
+            .build();
+
+        let text_area = Rect {
+            x: cell_area.x + 1,
+            y: cell_area.y + 1,
+            width: cell_area.width.saturating_sub(2),
+            height: cell_area.height.saturating_sub(2),
+        };
+
+        big_text.render(text_area, buf);
+    }
+}